@@ -15,8 +15,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let (r, mut w) = tokio_client::connect(&args[1])?;
     let mut watch = r.watch_radar().await;
-    let status = watch.get_game_robot_status().await;
-    let send_id = status.robot_id as u16;
+    let status = watch.get_game_robot_status().await.ok_or("Referee link closed")?;
+    let send_id = status.robot_id() as u16;
     info!("{:#?}", status);
 
     let mut itv = tokio::time::interval(Duration::from_millis(500));
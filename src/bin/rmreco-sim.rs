@@ -0,0 +1,43 @@
+use std::error::Error;
+use tokio::net::TcpListener;
+use tokio_serial::SerialPortBuilderExt;
+use tracing::info;
+
+use rmreco::serial::SerialConfig;
+use rmreco::simulator::{RefereeSimulator, Scenario, SimulatorConfig};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let subscriber = tracing_subscriber::FmtSubscriber::builder()
+        .with_max_level(tracing::Level::INFO)
+        .finish();
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let args: Vec<_> = std::env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <serial path | tcp://listen address> [robot id] [scenario.toml | scenario.json]", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut config = SimulatorConfig::default();
+    if let Some(robot_id) = args.get(2) {
+        config.robot_id = robot_id.parse()?;
+    }
+    let (mut simulator, _reports) = RefereeSimulator::new(config);
+    if let Some(path) = args.get(3) {
+        simulator = simulator.with_scenario(Scenario::load(path)?)?;
+        info!("Loaded scenario {}", path);
+    }
+
+    if let Some(addr) = args[1].strip_prefix("tcp://") {
+        let listener = TcpListener::bind(addr).await?;
+        info!("Waiting for robot on {}", addr);
+        let (stream, peer) = listener.accept().await?;
+        info!("Robot connected from {}", peer);
+        simulator.run(stream).await?;
+    } else {
+        let port = SerialConfig::default().builder(&args[1]).open_native_async()?;
+        simulator.run(port).await?;
+    }
+    Ok(())
+}
@@ -1,21 +1,33 @@
 use std::{io, thread};
-use std::io::Read;
-use std::sync::{Arc, atomic};
-use std::sync::atomic::{AtomicBool, AtomicU8};
-use std::time::Duration;
+use std::io::{Read, Write};
+use std::sync::{Arc, atomic, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8};
+use std::time::{Duration, Instant};
 
-use crossbeam_channel::{Receiver, unbounded};
-use deku::prelude::*;
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender, TrySendError, unbounded};
 use serialport;
 use serialport::SerialPort;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 
+use crate::hub::{Lagged, MessageFilter};
 use crate::proto;
+use crate::proto::parser::{Diagnostic, FrameParser, Parsed};
+use crate::record::{FlushPolicy, Recorder};
+use crate::serial::{Backoff, ConnectionState, PortSelector, SerialConfig};
+use crate::state::RefereeState;
+use crate::stats::{LinkStats, LinkStatsSnapshot};
 
 pub struct RefereeClient {
-    port: Box<dyn SerialPort>,
+    /// 仅用于写入，重连后由读取线程替换
+    port: Arc<Mutex<Box<dyn SerialPort>>>,
+    selector: PortSelector,
+    config: SerialConfig,
+    reconnect: Option<Backoff>,
+    state: Arc<Mutex<ConnectionState>>,
     // read_thread: Option<thread::JoinHandle<io::Result<()>>>,
     background_reader: Option<BackgroundReader>,
+    stats: Arc<Mutex<LinkStats>>,
+    recorder: Option<Recorder<Box<dyn Write + Send>>>,
 }
 
 pub struct BackgroundReader {
@@ -40,12 +52,46 @@ impl Drop for BackgroundReader {
 
 impl RefereeClient {
     pub fn try_new(path: &str) -> anyhow::Result<Self> {
-        let port = serialport::new(path, 115200)
-            .timeout(Duration::from_millis(1000)).open()?;
-        Ok(Self { port, background_reader: None })
+        Self::try_new_with_config(path, SerialConfig::default())
     }
 
+    /// 按 `config` 中的波特率、超时、协议版本等参数打开串口，重连时沿用同样的参数
+    pub fn try_new_with_config(selector: impl Into<PortSelector>, config: SerialConfig) -> anyhow::Result<Self> {
+        let selector = selector.into();
+        let path = selector.resolve()?;
+        let port = config.builder(&path).open()?;
+        Ok(Self {
+            port: Arc::new(Mutex::new(port)),
+            selector,
+            config,
+            reconnect: None,
+            state: Arc::new(Mutex::new(ConnectionState::Connected(path))),
+            background_reader: None,
+            stats: Default::default(),
+            recorder: None,
+        })
+    }
+
+    /// 之后启动的读取线程在串口断开时按 `backoff` 重新查找并打开串口，而不是退出
+    pub fn enable_reconnect(&mut self, backoff: Backoff) {
+        self.reconnect = Some(backoff);
+    }
+
+    pub fn connection_state(&self) -> ConnectionState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// 发送消息，`data_length` 由编码结果自动计算
+    pub fn send_message(&mut self, message: proto::Message) -> anyhow::Result<()> {
+        let data_length = message.data_length()?;
+        self.send_message_with_known_data_length(message, data_length)
+    }
+
+    /// 串口断开、等待重连期间返回 [`io::ErrorKind::NotConnected`]，帧不会被缓存重发
     pub fn send_message_with_known_data_length(&mut self, message: proto::Message, data_length: u16) -> anyhow::Result<()> {
+        if let ConnectionState::Disconnected(reason) = self.connection_state() {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, reason).into());
+        }
         // unsafe {
         //     static mut SEQ: u8 = 0;
         //     buf[3] = SEQ;
@@ -60,16 +106,8 @@ impl RefereeClient {
             message,
             crc_frame_tail: 0,
         };
-        let mut buf: Vec<u8> = frame.to_bytes()?;
-
-        let crc8 = proto::crc::CRC_8.checksum(&buf[..4]);
-        buf[4] = crc8;
-        let crc16 = proto::crc::CRC_16.checksum(&buf[..buf.len() - 2]).to_le_bytes();
-        // buf[buf.len() - 2..buf.len()] = crc_16.to_le_bytes();
-        let len = buf.len();
-        buf[len - 2] = crc16[0];
-        buf[len - 1] = crc16[1];
-        self.port.write_all(&buf)?;
+        let buf = frame.to_wire_bytes()?;
+        self.port.lock().unwrap().write_all(&buf)?;
         Ok(())
     }
 
@@ -77,10 +115,23 @@ impl RefereeClient {
     ///
     /// 不论雷达站属于哪一方，都以小地图左下角（红方补给区）为原点，双方对向为第一维，范围为 `(0.0..28.0, 0.0..15.0)`
     pub fn send_minimap_receipt(&mut self, target_robot_id: u16, target_position: (f32, f32)) -> anyhow::Result<()> {
-        self.send_message_with_known_data_length(proto::Message::MinimapReceipt {
+        self.send_message(proto::Message::MinimapReceipt {
             target_robot_id,
             target_position,
-        }, 10)
+        })
+    }
+
+    /// 当前链路统计，见 [`LinkStatsSnapshot`]
+    pub fn link_stats(&self) -> LinkStatsSnapshot {
+        self.stats.lock().unwrap().snapshot()
+    }
+
+    /// 将之后启动的读取线程收到的原始字节录制到 `out`，格式见 [`crate::record`]
+    ///
+    /// `out` 外包一层缓冲，读取线程结束时写出剩余的记录。
+    pub fn record_to(&mut self, out: impl Write + Send + 'static, flush_policy: FlushPolicy) {
+        let out: Box<dyn Write + Send> = Box::new(io::BufWriter::new(out));
+        self.recorder = Some(Recorder::new(out).flush_policy(flush_policy));
     }
 
     pub fn join_read_thread(&mut self) -> anyhow::Result<()> {
@@ -92,94 +143,251 @@ impl RefereeClient {
         Ok(())
     }
 
+    /// 启动后台读取线程并把收到的所有消息汇总为 [`RefereeState`]
+    pub fn spawn_state_thread(&mut self) -> anyhow::Result<Arc<Mutex<RefereeState>>> {
+        let receiver = self.spawn_read_thread()?;
+        let state = Arc::new(Mutex::new(RefereeState::new()));
+        let state_clone = state.clone();
+        thread::spawn(move || {
+            for frame in receiver {
+                state_clone.lock().unwrap().update(frame.message);
+            }
+        });
+        Ok(state)
+    }
+
+    /// 启动后台读取线程并通过 [`RefereeHub`] 分发给多个订阅者，每个订阅者最多缓存 `capacity` 帧
+    pub fn spawn_hub(&mut self, capacity: usize) -> anyhow::Result<RefereeHub> {
+        let receiver = self.spawn_read_thread_with_diagnostics()?;
+        Ok(RefereeHub::spawn(receiver, capacity))
+    }
+
+    /// 启动后台读取线程，诊断信息只记录到日志与链路统计中
     pub fn spawn_read_thread(&mut self) -> anyhow::Result<Receiver<proto::Frame2>> {
-        let clone = self.port.try_clone()?;
         let (sender, receiver) = unbounded();
+        self.spawn_reader(move |parsed| match parsed {
+            Ok(frame) => sender.send(frame).is_ok(),
+            Err(_) => true,
+        })?;
+        Ok(receiver)
+    }
+
+    /// 启动后台读取线程，损坏或无法解码的数据以 [`Diagnostic`] 的形式与正常帧一同送出
+    pub fn spawn_read_thread_with_diagnostics(&mut self) -> anyhow::Result<Receiver<Result<proto::Frame2, Diagnostic>>> {
+        let (sender, receiver) = unbounded();
+        self.spawn_reader(move |parsed| sender.send(parsed).is_ok())?;
+        Ok(receiver)
+    }
+
+    /// `emit` 返回 `false` 表示接收端已被丢弃，读取线程随即结束
+    fn spawn_reader(
+        &mut self,
+        mut emit: impl FnMut(Result<proto::Frame2, Diagnostic>) -> bool + Send + 'static,
+    ) -> anyhow::Result<()> {
+        let clone = self.port.lock().unwrap().try_clone()?;
+        let version = self.config.version;
+        let stats = self.stats.clone();
+        let mut recorder = self.recorder.take();
+        let selector = self.selector.clone();
+        let config = self.config.clone();
+        let reconnect = self.reconnect;
+        let writer_port = self.port.clone();
+        let state = self.state.clone();
 
         let should_stop = Arc::new(AtomicBool::new(false));
         let should_stop_clone = should_stop.clone();
         let thread = thread::spawn(move || -> io::Result<()> {
-            let mut buf_reader = io::BufReader::new(clone);
+            let mut port = clone;
+            let mut parser = FrameParser::new(version);
+            let mut buf = vec![0u8; config.read_buffer_size];
             while !should_stop_clone.load(atomic::Ordering::Relaxed) {
-                let mut header_buf = [0u8; 5];
-                let mut skip_count = 0;
-                while !should_stop_clone.load(atomic::Ordering::Relaxed) {
-                    // let mut buf = [0u8; 1];
-                    if let Err(err) = buf_reader.read_exact(&mut header_buf[0..=0]) {
-                        match err.kind() {
-                            io::ErrorKind::UnexpectedEof => {
-                                error!("Unexpected EOF, probably disconnected!");
-                                Err(err)?;
+                let read = match port.read(&mut buf) {
+                    Ok(0) => Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                    Ok(read) => Ok(read),
+                    Err(err) if err.kind() == io::ErrorKind::TimedOut => continue,
+                    Err(err) => Err(err),
+                };
+                let read = match read {
+                    Ok(read) => read,
+                    Err(err) => {
+                        error!("Serial port disconnected: {}", err);
+                        *state.lock().unwrap() = ConnectionState::Disconnected(err.to_string());
+                        let Some(backoff) = reconnect else { return Err(err) };
+                        match reopen(&selector, &config, backoff, &should_stop_clone, &writer_port, &state) {
+                            Some(reopened) => {
+                                port = reopened;
+                                continue;
                             }
-                            _ => { Err(err)?; }
+                            None => break,
                         }
                     }
-                    if header_buf[0] == 0xA5 { break; }
-                    // if buf[0] == 0xA5 { break; }
-                    skip_count += 1;
+                };
+                if let Some(recorder) = &mut recorder {
+                    if let Err(err) = recorder.record(&buf[..read]) {
+                        warn!("Failed to record: {}", err);
+                    }
                 }
-                if should_stop_clone.load(atomic::Ordering::Relaxed) { break; }
-
-                if skip_count > 0 {
-                    warn!("Skipped {} bytes", skip_count);
+                parser.push(&buf[..read]);
+                for parsed in &mut parser {
+                    let item = match parsed {
+                        Parsed::Frame(frame) => {
+                            stats.lock().unwrap().record_frame(&frame);
+                            Ok(frame)
+                        }
+                        Parsed::Diagnostic(diagnostic) => {
+                            warn!("{}", diagnostic);
+                            stats.lock().unwrap().record_diagnostic(&diagnostic);
+                            Err(diagnostic)
+                        }
+                    };
+                    if !emit(item) {
+                        info!("Receiver dropped, stopping read thread");
+                        return Ok(());
+                    }
                 }
+            }
+            info!("should_stop is true, stopping read thread");
+            *state.lock().unwrap() = ConnectionState::Closed;
+            Ok(())
+        });
+        self.background_reader = Some(BackgroundReader { thread: Some(thread), /*receiver,*/ should_stop });
+        Ok(())
+    }
+}
 
-                // let mut header_buf = [0u8; 4];
-                buf_reader.read_exact(&mut header_buf[1..=4])?;
-                let data_length = u16::from_le_bytes([header_buf[1], header_buf[2]]) as usize;
-                if data_length > 119 {
-                    warn!("Invalid data length: {}", data_length);
-                    continue;
-                }
-                let calculated_crc = proto::crc::CRC_8.checksum(&header_buf[0..4]);
-                if calculated_crc != header_buf[4] { warn!("Wrong CRC8: {:?} summed to {} instead of {}", &header_buf[0..4], calculated_crc,header_buf[4]); }
+/// 重新打开串口直到成功或被要求停止，成功时替换写入用的串口并返回读取用的串口
+fn reopen(
+    selector: &PortSelector,
+    config: &SerialConfig,
+    backoff: Backoff,
+    should_stop: &AtomicBool,
+    writer_port: &Mutex<Box<dyn SerialPort>>,
+    state: &Mutex<ConnectionState>,
+) -> Option<Box<dyn SerialPort>> {
+    let mut attempt = 0;
+    while !should_stop.load(atomic::Ordering::Relaxed) {
+        let opened = selector.resolve()
+            .and_then(|path| config.builder(&path).open().map(|port| (path, port)))
+            .and_then(|(path, port)| port.try_clone().map(|writer| (path, port, writer)));
+        match opened {
+            Ok((path, port, writer)) => {
+                info!("Reconnected to {}", path);
+                *writer_port.lock().unwrap() = writer;
+                *state.lock().unwrap() = ConnectionState::Connected(path);
+                return Some(port);
+            }
+            Err(err) => {
+                let delay = backoff.delay(attempt);
+                attempt = attempt.saturating_add(1);
+                debug!("Failed to reopen serial port: {}, retrying in {:?}", err, delay);
+                *state.lock().unwrap() = ConnectionState::Disconnected(err.to_string());
+                sleep_unless_stopped(delay, should_stop);
+            }
+        }
+    }
+    None
+}
 
+/// 分段等待，被要求停止时提前返回
+fn sleep_unless_stopped(delay: Duration, should_stop: &AtomicBool) {
+    const STEP: Duration = Duration::from_millis(50);
+    let deadline = Instant::now() + delay;
+    while !should_stop.load(atomic::Ordering::Relaxed) {
+        let now = Instant::now();
+        if now >= deadline { break; }
+        thread::sleep((deadline - now).min(STEP));
+    }
+}
 
-                let mut buf = vec![0u8; 7 + data_length + 2];
-                // buf[0] = 0xA5;
-                // buf[1..5].copy_from_slice(&header_buf);
-                buf[0..5].copy_from_slice(&header_buf);
-                buf_reader.read_exact(&mut buf[5..7 + data_length + 2])?;
+struct Subscriber {
+    filter: MessageFilter,
+    sender: Sender<Arc<proto::Frame2>>,
+    dropped: Arc<AtomicU64>,
+}
 
-                match proto::Frame2::from_bytes((&buf, 0)) {
-                    Ok(((_, rest_byte_size), frame)) => {
-                        if rest_byte_size != 0 {
-                            warn!("Invalid frame, rest byte size: {}", rest_byte_size);
-                            continue;
-                        }
+impl Subscriber {
+    /// 返回 `false` 表示订阅者已被丢弃
+    fn offer(&self, frame: &Arc<proto::Frame2>) -> bool {
+        if !self.filter.matches(&frame.message) { return true; }
+        match self.sender.try_send(frame.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, atomic::Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
 
-                        let calculated_crc = Self::CRC_16.checksum(&buf[0..7 + data_length]);
+/// 把读取线程收到的帧分发给多个订阅者，读取线程结束后订阅者取完剩余的帧即断开
+pub struct RefereeHub {
+    /// 读取线程结束后为 `None`
+    subscribers: Arc<Mutex<Option<Vec<Subscriber>>>>,
+    capacity: usize,
+}
 
-                        if calculated_crc != u16::from_le_bytes([buf[7 + data_length], buf[7 + data_length + 1]]) {
-                            // warn!("Wrong CRC16");
-                            warn!("Wrong CRC16: {:?} summed to {} instead of {}", &buf[0..7 + data_length], calculated_crc, u16::from_le_bytes([buf[7 + data_length], buf[7 + data_length + 1]]) );
-                        }
+impl RefereeHub {
+    /// 在新线程中分发 `receiver` 收到的帧，诊断信息被忽略
+    pub fn spawn(receiver: Receiver<Result<proto::Frame2, Diagnostic>>, capacity: usize) -> Self {
+        let subscribers = Arc::new(Mutex::new(Some(Vec::<Subscriber>::new())));
+        let subscribers_clone = subscribers.clone();
+        thread::spawn(move || {
+            for frame in receiver.into_iter().flatten() {
+                let frame = Arc::new(frame);
+                if let Some(subscribers) = subscribers_clone.lock().unwrap().as_mut() {
+                    subscribers.retain(|subscriber| subscriber.offer(&frame));
+                }
+            }
+            // 丢弃所有发送端，使订阅者取完剩余的帧后断开
+            subscribers_clone.lock().unwrap().take();
+        });
+        Self { subscribers, capacity }
+    }
 
-                        // match frame.message {
-                        //     _ => todo!(),
-                        // };
+    /// 读取线程已结束时返回的订阅立即断开
+    pub fn subscribe(&self, filter: MessageFilter) -> HubSubscription {
+        let (sender, receiver) = bounded(self.capacity);
+        let dropped = Arc::new(AtomicU64::new(0));
+        if let Some(subscribers) = self.subscribers.lock().unwrap().as_mut() {
+            subscribers.push(Subscriber { filter, sender, dropped: dropped.clone() });
+        }
+        HubSubscription { receiver, dropped }
+    }
 
-                        sender.send(frame).unwrap();
-                        // info!("{:?}", frame.message);
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().as_ref().map_or(0, Vec::len)
+    }
 
-                        // println!("{:?}", frame.message);
-                    }
-                    Err(err) => {
-                        warn!("Invalid frame: {:?}, bytes: {:?}", err, &buf);
-                        continue;
-                    }
-                }
+    pub fn is_closed(&self) -> bool {
+        self.subscribers.lock().unwrap().is_none()
+    }
+}
+
+pub struct HubSubscription {
+    receiver: Receiver<Arc<proto::Frame2>>,
+    dropped: Arc<AtomicU64>,
+}
 
-                // let ((_, rest_byte_size), frame) = Frame2::from_bytes((&buf, 0))?;
-                // assert_eq!(rest_byte_size, 0);
-                //
-                // println!("{:?}", frame.message);
+impl HubSubscription {
+    fn take_lagged(&self) -> Option<Lagged> {
+        match self.dropped.swap(0, atomic::Ordering::Relaxed) {
+            0 => None,
+            dropped => {
+                warn!("Subscriber lagged behind, {} frames dropped", dropped);
+                Some(Lagged(dropped))
             }
-            info!("should_stop is true, stopping read thread");
-            Ok(())
-        });
-        let receiver_clone = receiver;
-        self.background_reader = Some(BackgroundReader { thread: Some(thread), /*receiver,*/ should_stop });
-        Ok(receiver_clone)
+        }
+    }
+
+    /// 接收下一个满足过滤条件的帧，处理过慢而丢帧时先返回一次 [`Lagged`]，读取线程结束后返回 `None`
+    pub fn recv(&self) -> Option<Result<Arc<proto::Frame2>, Lagged>> {
+        if let Some(lagged) = self.take_lagged() { return Some(Err(lagged)); }
+        self.receiver.recv().ok().map(Ok)
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Result<Arc<proto::Frame2>, Lagged>, RecvTimeoutError> {
+        if let Some(lagged) = self.take_lagged() { return Ok(Err(lagged)); }
+        self.receiver.recv_timeout(timeout).map(Ok)
     }
 }
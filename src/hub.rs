@@ -0,0 +1,80 @@
+//! 多订阅者分发所用的消息过滤条件，具体的分发实现见各客户端的 `RefereeHub`
+
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use crate::proto::{Message, MessageKind};
+
+/// 订阅者处理过慢时被丢弃的帧数
+///
+/// 计数可能包含不满足该订阅者过滤条件的帧。
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Subscriber lagged behind, {0} frames were dropped")]
+pub struct Lagged(pub u64);
+
+/// 订阅过滤条件
+#[derive(Clone, Default)]
+pub enum MessageFilter {
+    #[default]
+    All,
+    Kinds(Vec<MessageKind>),
+    /// content_id 在范围内的 0x0301 机器人交互数据
+    Interactive(RangeInclusive<u16>),
+    /// 满足任一条件
+    Any(Vec<MessageFilter>),
+    Custom(Arc<dyn Fn(&Message) -> bool + Send + Sync>),
+}
+
+impl MessageFilter {
+    pub fn kind(kind: MessageKind) -> Self {
+        MessageFilter::Kinds(vec![kind])
+    }
+
+    pub fn kinds(kinds: impl IntoIterator<Item = MessageKind>) -> Self {
+        MessageFilter::Kinds(kinds.into_iter().collect())
+    }
+
+    pub fn interactive(content_id: u16) -> Self {
+        MessageFilter::Interactive(content_id..=content_id)
+    }
+
+    pub fn custom(f: impl Fn(&Message) -> bool + Send + Sync + 'static) -> Self {
+        MessageFilter::Custom(Arc::new(f))
+    }
+
+    pub fn or(self, other: MessageFilter) -> Self {
+        match self {
+            MessageFilter::Any(mut filters) => {
+                filters.push(other);
+                MessageFilter::Any(filters)
+            }
+            this => MessageFilter::Any(vec![this, other]),
+        }
+    }
+
+    pub fn matches(&self, message: &Message) -> bool {
+        match self {
+            MessageFilter::All => true,
+            MessageFilter::Kinds(kinds) => kinds.contains(&message.kind()),
+            MessageFilter::Interactive(content_ids) => match message {
+                Message::StudentInteractiveData(data) => content_ids.contains(&data.content_id),
+                _ => false,
+            },
+            MessageFilter::Any(filters) => filters.iter().any(|filter| filter.matches(message)),
+            MessageFilter::Custom(f) => f(message),
+        }
+    }
+}
+
+impl fmt::Debug for MessageFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageFilter::All => f.write_str("All"),
+            MessageFilter::Kinds(kinds) => f.debug_tuple("Kinds").field(kinds).finish(),
+            MessageFilter::Interactive(content_ids) => f.debug_tuple("Interactive").field(content_ids).finish(),
+            MessageFilter::Any(filters) => f.debug_tuple("Any").field(filters).finish(),
+            MessageFilter::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
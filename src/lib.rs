@@ -1,10 +1,22 @@
 #[cfg(test)]
 mod tests;
 
+pub mod hub;
 pub mod proto;
+pub mod record;
+pub mod send_queue;
+pub mod state;
+pub mod stats;
+pub mod ui;
+
+#[cfg(any(feature = "blocking_client", feature = "tokio_client"))]
+pub mod serial;
 
 #[cfg(feature = "blocking_client")]
 pub mod blocking_client;
 
 #[cfg(feature = "tokio_client")]
-pub mod tokio_client;
\ No newline at end of file
+pub mod tokio_client;
+
+#[cfg(feature = "simulator")]
+pub mod simulator;
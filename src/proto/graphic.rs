@@ -1,9 +1,45 @@
 use deku::prelude::*;
 use serde::{Serialize, Deserialize};
 
+/// 客户端允许的最大图层
+pub const MAX_LAYER: u8 = 9;
+const U11_MAX: u16 = (1 << 11) - 1;
+const U10_MAX: u16 = (1 << 10) - 1;
+const U9_MAX: u16 = (1 << 9) - 1;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum GraphicError {
+    #[error("Graphic name must be 1 to 3 bytes, got {0}")]
+    InvalidName(usize),
+    #[error("Layer {0} is out of range, at most {MAX_LAYER} allowed")]
+    InvalidLayer(u8),
+    #[error("graphic_type {graphic_type} does not match graphic data of type {expected}")]
+    TypeMismatch { graphic_type: u8, expected: u8 },
+    #[error("{field} = {value} is out of range, at most {max} allowed")]
+    OutOfRange { field: &'static str, value: u16, max: u16 },
+}
+
+/// 将 1 至 3 字节的名称以 0 补齐为 [`GraphicData::graphic_name`]
+pub fn graphic_name(name: impl AsRef<[u8]>) -> Result<[u8; 3], GraphicError> {
+    let name = name.as_ref();
+    if name.is_empty() || name.len() > 3 {
+        return Err(GraphicError::InvalidName(name.len()));
+    }
+    let mut graphic_name = [0u8; 3];
+    graphic_name[..name.len()].copy_from_slice(name);
+    Ok(graphic_name)
+}
+
+fn check(field: &'static str, value: u16, max: u16) -> Result<(), GraphicError> {
+    if value > max {
+        return Err(GraphicError::OutOfRange { field, value, max });
+    }
+    Ok(())
+}
+
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[deku(type = "u8")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[deku(id_type = "u8")]
 pub enum GraphicDeleteOperation {
     #[deku(id = "0")]
     Nop,
@@ -14,7 +50,7 @@ pub enum GraphicDeleteOperation {
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GraphicData {
     pub graphic_name: [u8; 3],
     pub operate_type: GraphicAddOperation,
@@ -27,9 +63,267 @@ pub struct GraphicData {
     pub graphic_data: GraphicEnum,
 }
 
+impl GraphicData {
+    pub fn line(name: impl AsRef<[u8]>, layer: u8, color: GraphicColor) -> GraphicBuilder<StraightLineRectangleData> {
+        GraphicBuilder::new(name, layer, color, GraphicEnum::StraightLine)
+    }
+
+    pub fn rectangle(name: impl AsRef<[u8]>, layer: u8, color: GraphicColor) -> GraphicBuilder<StraightLineRectangleData> {
+        GraphicBuilder::new(name, layer, color, GraphicEnum::Rectangle)
+    }
+
+    pub fn circle(name: impl AsRef<[u8]>, layer: u8, color: GraphicColor) -> GraphicBuilder<CircleData> {
+        GraphicBuilder::new(name, layer, color, GraphicEnum::Circle)
+    }
+
+    pub fn ellipse(name: impl AsRef<[u8]>, layer: u8, color: GraphicColor) -> GraphicBuilder<EllipseData> {
+        GraphicBuilder::new(name, layer, color, GraphicEnum::Ellipse)
+    }
+
+    pub fn arc(name: impl AsRef<[u8]>, layer: u8, color: GraphicColor) -> GraphicBuilder<ArcData> {
+        GraphicBuilder::new(name, layer, color, GraphicEnum::Arc)
+    }
+
+    pub fn floating_number(name: impl AsRef<[u8]>, layer: u8, color: GraphicColor) -> GraphicBuilder<FloatingNumberData> {
+        GraphicBuilder::new(name, layer, color, GraphicEnum::FloatingNumber)
+    }
+
+    pub fn integer(name: impl AsRef<[u8]>, layer: u8, color: GraphicColor) -> GraphicBuilder<IntegerData> {
+        GraphicBuilder::new(name, layer, color, GraphicEnum::Integer)
+    }
+
+    /// 字符内容随 [`super::StudentInteractiveDataType::GraphicDrawCharacter`] 另行发送
+    pub fn character(name: impl AsRef<[u8]>, layer: u8, color: GraphicColor) -> GraphicBuilder<CharacterData> {
+        GraphicBuilder::new(name, layer, color, GraphicEnum::Character)
+    }
+
+    /// 用于填充 `GraphicDraw2/5/7` 中未使用位置的空操作
+    pub fn nop() -> Self {
+        GraphicData {
+            graphic_name: [0; 3],
+            operate_type: GraphicAddOperation::Nop,
+            graphic_type: 0,
+            layer: 0,
+            color: GraphicColor::RedAndBlue,
+            graphic_data: GraphicEnum::StraightLine(Default::default()),
+        }
+    }
+
+    /// 检查图层、`graphic_type` 与各字段是否在协议允许的范围内，超出位宽的值在编码时会被截断
+    pub fn validate(&self) -> Result<(), GraphicError> {
+        if self.layer > MAX_LAYER {
+            return Err(GraphicError::InvalidLayer(self.layer));
+        }
+        let expected = self.graphic_data.graphic_type();
+        if self.graphic_type != expected {
+            return Err(GraphicError::TypeMismatch { graphic_type: self.graphic_type, expected });
+        }
+        self.graphic_data.validate()
+    }
+}
+
+/// 逐项设置图形参数，由 [`GraphicBuilder::build`] 统一检查
+///
+/// ```
+/// # use rmreco::proto::graphic::{GraphicColor, GraphicData};
+/// let line = GraphicData::line(b"aim", 1, GraphicColor::Green)
+///     .from(960, 400).to(960, 680).width(2)
+///     .build()?;
+/// assert_eq!(line.graphic_type, 0);
+/// # Ok::<(), rmreco::proto::graphic::GraphicError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct GraphicBuilder<T> {
+    name: Vec<u8>,
+    layer: u8,
+    color: GraphicColor,
+    operate_type: GraphicAddOperation,
+    data: T,
+    wrap: fn(T) -> GraphicEnum,
+}
+
+impl<T: Default> GraphicBuilder<T> {
+    fn new(name: impl AsRef<[u8]>, layer: u8, color: GraphicColor, wrap: fn(T) -> GraphicEnum) -> Self {
+        Self { name: name.as_ref().to_vec(), layer, color, operate_type: GraphicAddOperation::Add, data: T::default(), wrap }
+    }
+}
+
+impl<T> GraphicBuilder<T> {
+    /// 默认为 [`GraphicAddOperation::Add`]
+    pub fn operation(mut self, operate_type: GraphicAddOperation) -> Self {
+        self.operate_type = operate_type;
+        self
+    }
+
+    /// 名称不足 3 字节时以 0 补齐
+    pub fn build(self) -> Result<GraphicData, GraphicError> {
+        let graphic_name = graphic_name(&self.name)?;
+        let graphic_data = (self.wrap)(self.data);
+        let data = GraphicData {
+            graphic_name,
+            operate_type: self.operate_type,
+            graphic_type: graphic_data.graphic_type(),
+            layer: self.layer,
+            color: self.color,
+            graphic_data,
+        };
+        data.validate()?;
+        Ok(data)
+    }
+}
+
+impl GraphicBuilder<StraightLineRectangleData> {
+    /// 直线的起点或矩形的一个顶点
+    pub fn from(mut self, x: u16, y: u16) -> Self {
+        (self.data.start_x, self.data.start_y) = (x, y);
+        self
+    }
+
+    /// 直线的终点或矩形的对角顶点
+    pub fn to(mut self, x: u16, y: u16) -> Self {
+        (self.data.end_x, self.data.end_y) = (x, y);
+        self
+    }
+
+    pub fn width(mut self, width: u16) -> Self {
+        self.data.width = width;
+        self
+    }
+}
+
+impl GraphicBuilder<CircleData> {
+    pub fn center(mut self, x: u16, y: u16) -> Self {
+        (self.data.x, self.data.y) = (x, y);
+        self
+    }
+
+    pub fn radius(mut self, radius: u16) -> Self {
+        self.data.radius = radius;
+        self
+    }
+
+    pub fn width(mut self, width: u16) -> Self {
+        self.data.width = width;
+        self
+    }
+}
+
+impl GraphicBuilder<EllipseData> {
+    pub fn center(mut self, x: u16, y: u16) -> Self {
+        (self.data.x, self.data.y) = (x, y);
+        self
+    }
+
+    pub fn half_axes(mut self, half_x_length: u16, half_y_length: u16) -> Self {
+        (self.data.half_x_length, self.data.half_y_length) = (half_x_length, half_y_length);
+        self
+    }
+
+    pub fn width(mut self, width: u16) -> Self {
+        self.data.width = width;
+        self
+    }
+}
+
+impl GraphicBuilder<ArcData> {
+    pub fn center(mut self, x: u16, y: u16) -> Self {
+        (self.data.x, self.data.y) = (x, y);
+        self
+    }
+
+    pub fn half_axes(mut self, half_x_length: u16, half_y_length: u16) -> Self {
+        (self.data.half_x_length, self.data.half_y_length) = (half_x_length, half_y_length);
+        self
+    }
+
+    /// 单位为度，正上方为 0，顺时针增大
+    pub fn angles(mut self, start_angle: u16, end_angle: u16) -> Self {
+        (self.data.start_angle, self.data.end_angle) = (start_angle, end_angle);
+        self
+    }
+
+    pub fn width(mut self, width: u16) -> Self {
+        self.data.width = width;
+        self
+    }
+}
+
+impl GraphicBuilder<FloatingNumberData> {
+    pub fn at(mut self, x: u16, y: u16) -> Self {
+        (self.data.start_x, self.data.start_y) = (x, y);
+        self
+    }
+
+    pub fn font_size(mut self, font_size: u16) -> Self {
+        self.data.font_size = font_size;
+        self
+    }
+
+    pub fn decimal_digit(mut self, decimal_digit: u16) -> Self {
+        self.data.decimal_digit = decimal_digit;
+        self
+    }
+
+    pub fn width(mut self, width: u16) -> Self {
+        self.data.width = width;
+        self
+    }
+
+    /// 按协议以 1000 倍的整数发送，超出 `i32` 范围时取边界值
+    pub fn value(mut self, value: f32) -> Self {
+        self.data.value = (value as f64 * 1000.0).round() as i32;
+        self
+    }
+}
+
+impl GraphicBuilder<IntegerData> {
+    pub fn at(mut self, x: u16, y: u16) -> Self {
+        (self.data.start_x, self.data.start_y) = (x, y);
+        self
+    }
+
+    pub fn font_size(mut self, font_size: u16) -> Self {
+        self.data.font_size = font_size;
+        self
+    }
+
+    pub fn width(mut self, width: u16) -> Self {
+        self.data.width = width;
+        self
+    }
+
+    pub fn value(mut self, value: i32) -> Self {
+        self.data.value = value;
+        self
+    }
+}
+
+impl GraphicBuilder<CharacterData> {
+    pub fn at(mut self, x: u16, y: u16) -> Self {
+        (self.data.x, self.data.y) = (x, y);
+        self
+    }
+
+    pub fn font_size(mut self, font_size: u16) -> Self {
+        self.data.font_size = font_size;
+        self
+    }
+
+    /// 字符长度
+    pub fn length(mut self, length: u16) -> Self {
+        self.data.decimal_digit = length;
+        self
+    }
+
+    pub fn width(mut self, width: u16) -> Self {
+        self.data.width = width;
+        self
+    }
+}
+
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[deku(type = "u8")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[deku(id_type = "u8")]
 #[deku(bits = 3)]
 pub enum GraphicAddOperation {
     #[deku(id = "0")]
@@ -43,7 +337,7 @@ pub enum GraphicAddOperation {
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[deku(ctx = "graphic_type: u8", id = "graphic_type")]
 pub enum GraphicEnum {
     #[deku(id = "0")]
@@ -64,8 +358,78 @@ pub enum GraphicEnum {
     Character(CharacterData),
 }
 
+impl GraphicEnum {
+    /// 对应 [`GraphicData::graphic_type`] 的值
+    pub fn graphic_type(&self) -> u8 {
+        match self {
+            GraphicEnum::StraightLine(_) => 0,
+            GraphicEnum::Rectangle(_) => 1,
+            GraphicEnum::Circle(_) => 2,
+            GraphicEnum::Ellipse(_) => 3,
+            GraphicEnum::Arc(_) => 4,
+            GraphicEnum::FloatingNumber(_) => 5,
+            GraphicEnum::Integer(_) => 6,
+            GraphicEnum::Character(_) => 7,
+        }
+    }
+
+    fn validate(&self) -> Result<(), GraphicError> {
+        match self {
+            GraphicEnum::StraightLine(data) | GraphicEnum::Rectangle(data) => {
+                check("width", data.width, U10_MAX)?;
+                check("start_x", data.start_x, U11_MAX)?;
+                check("start_y", data.start_y, U11_MAX)?;
+                check("end_x", data.end_x, U11_MAX)?;
+                check("end_y", data.end_y, U11_MAX)
+            }
+            GraphicEnum::Circle(data) => {
+                check("width", data.width, U10_MAX)?;
+                check("x", data.x, U11_MAX)?;
+                check("y", data.y, U11_MAX)?;
+                check("radius", data.radius, U10_MAX)
+            }
+            GraphicEnum::Ellipse(data) => {
+                check("width", data.width, U10_MAX)?;
+                check("x", data.x, U11_MAX)?;
+                check("y", data.y, U11_MAX)?;
+                check("half_x_length", data.half_x_length, U11_MAX)?;
+                check("half_y_length", data.half_y_length, U11_MAX)
+            }
+            GraphicEnum::Arc(data) => {
+                check("start_angle", data.start_angle, U9_MAX)?;
+                check("end_angle", data.end_angle, U9_MAX)?;
+                check("width", data.width, U10_MAX)?;
+                check("x", data.x, U11_MAX)?;
+                check("y", data.y, U11_MAX)?;
+                check("half_x_length", data.half_x_length, U11_MAX)?;
+                check("half_y_length", data.half_y_length, U11_MAX)
+            }
+            GraphicEnum::FloatingNumber(data) => {
+                check("font_size", data.font_size, U9_MAX)?;
+                check("decimal_digit", data.decimal_digit, U9_MAX)?;
+                check("width", data.width, U10_MAX)?;
+                check("start_x", data.start_x, U11_MAX)?;
+                check("start_y", data.start_y, U11_MAX)
+            }
+            GraphicEnum::Integer(data) => {
+                check("font_size", data.font_size, U9_MAX)?;
+                check("width", data.width, U10_MAX)?;
+                check("start_x", data.start_x, U11_MAX)?;
+                check("start_y", data.start_y, U11_MAX)
+            }
+            GraphicEnum::Character(data) => {
+                check("font_size", data.font_size, U9_MAX)?;
+                check("decimal_digit", data.decimal_digit, U9_MAX)?;
+                check("width", data.width, U10_MAX)?;
+                check("x", data.x, U11_MAX)?;
+                check("y", data.y, U11_MAX)
+            }
+        }
+    }
+}
+
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct StraightLineRectangleData {
     // start_angle: 9, end_angle: 9
     #[deku(pad_bits_before = "18", bits = 10)]
@@ -82,7 +446,7 @@ pub struct StraightLineRectangleData {
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct CircleData {
     // start_angle: 9, end_angle: 9
     #[deku(pad_bits_before = "18", bits = 10)]
@@ -97,7 +461,7 @@ pub struct CircleData {
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct EllipseData {
     // start_angle: 9, end_angle: 9
     #[deku(pad_bits_before = "18", bits = 10)]
@@ -114,7 +478,7 @@ pub struct EllipseData {
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ArcData {
     #[deku(bits = 9)]
     pub start_angle: u16,
@@ -134,7 +498,7 @@ pub struct ArcData {
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct FloatingNumberData {
     #[deku(bits = 9)]
     pub font_size: u16,
@@ -150,7 +514,7 @@ pub struct FloatingNumberData {
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct IntegerData {
     #[deku(bits = 9)]
     pub font_size: u16,
@@ -165,7 +529,7 @@ pub struct IntegerData {
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct CharacterData {
     #[deku(bits = 9)]
     pub font_size: u16,
@@ -181,8 +545,8 @@ pub struct CharacterData {
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[deku(type = "u8")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[deku(id_type = "u8")]
 #[deku(bits = 4)]
 pub enum GraphicColor {
     #[deku(id = "0")]
@@ -1,3 +1,5 @@
+use std::io::Cursor;
+
 use deku::prelude::*;
 use serde::{Serialize, Deserialize};
 
@@ -10,34 +12,171 @@ pub mod crc {
         init: 0xffff,
         ..crc::CRC_16_KERMIT
     };
-    pub(crate) const CRC_8: crc::Crc<u8> = crc::Crc::<u8>::new(&CRC_8_ALGORITHM);
-    pub(crate) const CRC_16: crc::Crc<u16> = crc::Crc::<u16>::new(&CRC_16_ALGORITHM);
+    pub const CRC_8: crc::Crc<u8> = crc::Crc::<u8>::new(&CRC_8_ALGORITHM);
+    pub const CRC_16: crc::Crc<u16> = crc::Crc::<u16>::new(&CRC_16_ALGORITHM);
 }
 
+/// 帧起始字节
+pub const SOF: u8 = 0xA5;
+/// 帧头长度，包括 SOF、data_length、seq 与 CRC8
+pub const HEADER_SIZE: usize = 5;
+/// 帧头、命令码与帧尾 CRC16 的总长度
+pub const FRAME_OVERHEAD: usize = 9;
+/// data_length 的上限
+pub const MAX_DATA_LENGTH: u16 = 119;
+
 pub mod graphic;
 pub mod proprietary;
 pub mod id;
+pub mod parser;
+pub mod v2023;
+pub mod v2024;
 
 #[cfg(test)]
 mod tests;
 
+/// 裁判系统串口协议版本，按赛季区分
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ProtocolVersion {
+    #[default]
+    V2023,
+    V2024,
+    /// 目前按 2024 赛季的布局编解码，2025 赛季调整的字段尚未单独建模，
+    /// 新增的增益点位可从 [`v2024::RFIDStatus::reserved`] 读取
+    V2025,
+}
+
+impl ProtocolVersion {
+    /// 编解码时实际使用的布局版本
+    pub fn layout(self) -> Self {
+        match self {
+            ProtocolVersion::V2025 => ProtocolVersion::V2024,
+            version => version,
+        }
+    }
+}
+
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[deku(magic = b"\xA5")]
+#[deku(ctx = "version: ProtocolVersion", ctx_default = "ProtocolVersion::default()")]
 pub struct Frame2 {
     // pub sof: u8,
     pub data_length: u16,
     pub seq: u8,
     pub crc8: u8,
-    #[deku(ctx = "*data_length")]
+    #[deku(ctx = "*data_length + 9, version")]
     pub message: Message,
     pub crc_frame_tail: u16,
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum FrameError {
+    #[error("Frame is incomplete, {needed} more bytes needed")]
+    Incomplete { needed: usize },
+    #[error("Frame starts with {0:#04x} instead of 0xA5")]
+    InvalidSof(u8),
+    #[error("Data length {0} exceeds the maximum of {}", MAX_DATA_LENGTH)]
+    DataLengthTooLarge(u16),
+    #[error("Wrong CRC8: calculated {calculated:#04x}, received {received:#04x}")]
+    Crc8Mismatch { calculated: u8, received: u8 },
+    #[error("Wrong CRC16: calculated {calculated:#06x}, received {received:#06x}")]
+    Crc16Mismatch { calculated: u16, received: u16 },
+    #[error("Declared data length {declared} disagrees with encoded payload length {actual}")]
+    DataLengthMismatch { declared: u16, actual: usize },
+    #[error("{0} trailing bytes after the frame")]
+    TrailingBytes(usize),
+    #[error("Internal decoder error")]
+    Deku(#[from] DekuError),
+}
+
+impl Frame2 {
+    /// 以编码后的消息长度填写 `data_length`，CRC 在编码时计算
+    pub fn new(seq: u8, message: Message) -> Result<Self, DekuError> {
+        let data_length = message.data_length()?;
+        Ok(Self { data_length, seq, crc8: 0, message, crc_frame_tail: 0 })
+    }
+
+    /// 编码为可直接发送的完整帧，包括 CRC8 与 CRC16
+    pub fn encode(seq: u8, message: Message) -> Result<Vec<u8>, FrameError> {
+        Self::new(seq, message)?.to_wire_bytes()
+    }
+
+    /// 按 `data_length` 编码当前帧并填写 CRC，忽略 `crc8` 与 `crc_frame_tail` 字段原有的值
+    ///
+    /// 布局随赛季变化的消息按其自身的布局版本编码。
+    pub fn to_wire_bytes(&self) -> Result<Vec<u8>, FrameError> {
+        let mut buf = encode_with_ctx(self, self.message.version().unwrap_or_default())?;
+        if self.data_length as usize + FRAME_OVERHEAD != buf.len() {
+            return Err(FrameError::DataLengthMismatch { declared: self.data_length, actual: buf.len() - FRAME_OVERHEAD });
+        }
+        buf[4] = crc::CRC_8.checksum(&buf[..4]);
+        let len = buf.len();
+        let crc16 = crc::CRC_16.checksum(&buf[..len - 2]).to_le_bytes();
+        buf[len - 2..].copy_from_slice(&crc16);
+        Ok(buf)
+    }
+
+    /// 校验帧头并返回整帧长度，`header` 至少包含 [`HEADER_SIZE`] 个字节
+    pub fn wire_size(header: &[u8]) -> Result<usize, FrameError> {
+        if header.len() < HEADER_SIZE {
+            return Err(FrameError::Incomplete { needed: HEADER_SIZE - header.len() });
+        }
+        if header[0] != SOF {
+            return Err(FrameError::InvalidSof(header[0]));
+        }
+        let calculated = crc::CRC_8.checksum(&header[..4]);
+        if calculated != header[4] {
+            return Err(FrameError::Crc8Mismatch { calculated, received: header[4] });
+        }
+        let data_length = u16::from_le_bytes([header[1], header[2]]);
+        if data_length > MAX_DATA_LENGTH {
+            return Err(FrameError::DataLengthTooLarge(data_length));
+        }
+        Ok(data_length as usize + FRAME_OVERHEAD)
+    }
+
+    /// 按默认协议版本解码一个完整帧，见 [`Frame2::decode_with_version`]
+    pub fn decode(bytes: &[u8]) -> Result<Self, FrameError> {
+        Self::decode_with_version(bytes, ProtocolVersion::default())
+    }
+
+    /// 解码恰好一个完整帧，依次校验 SOF、CRC8、长度与 CRC16
+    pub fn decode_with_version(bytes: &[u8], version: ProtocolVersion) -> Result<Self, FrameError> {
+        let size = Self::wire_size(bytes)?;
+        if bytes.len() < size {
+            return Err(FrameError::Incomplete { needed: size - bytes.len() });
+        }
+        if bytes.len() > size {
+            return Err(FrameError::TrailingBytes(bytes.len() - size));
+        }
+        let calculated = crc::CRC_16.checksum(&bytes[..size - 2]);
+        let received = u16::from_le_bytes([bytes[size - 2], bytes[size - 1]]);
+        if calculated != received {
+            return Err(FrameError::Crc16Mismatch { calculated, received });
+        }
+        let mut reader = Reader::new(Cursor::new(bytes));
+        let frame = Self::from_reader_with_ctx(&mut reader, version)?;
+        let rest = bytes.len() - reader.bits_read / 8;
+        if rest != 0 {
+            return Err(FrameError::TrailingBytes(rest));
+        }
+        Ok(frame)
+    }
+}
+
+fn encode_with_ctx<T: DekuWriter<Ctx>, Ctx>(value: &T, ctx: Ctx) -> Result<Vec<u8>, DekuError> {
+    let mut buf = Vec::new();
+    let mut writer = Writer::new(Cursor::new(&mut buf));
+    value.to_writer(&mut writer, ctx)?;
+    writer.finalize()?;
+    Ok(buf)
+}
+
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[deku(type = "u16")]
-#[deku(ctx = "frame_size: u16")]
+#[deku(id_type = "u16")]
+#[deku(ctx = "frame_size: u16, version: ProtocolVersion")]
 #[serde(tag = "t", content = "c", rename_all = "snake_case")]
 pub enum Message {
     #[deku(id = "0x0001")]
@@ -50,110 +189,35 @@ pub enum Message {
         blue: TeamHP,
     },
     #[deku(id = "0x0101")]
-    EventData(EventData),
+    EventData(#[deku(ctx = "version")] EventData),
     #[deku(id = "0x0102")]
-    SupplyProjectileAction {
-        supplier: ProjectileSupplier,
-        robot: ProjectileReloadingRobot,
-        outlet_status: ProjectileOutletStatus,
-        supplied_number: SuppliedProjectileNumber,
-    },
+    SupplyProjectileAction(SupplyProjectileAction),
     #[deku(id = "0x0104")]
-    RefereeWarning(RefereeWarning),
+    RefereeWarning(#[deku(ctx = "version")] RefereeWarning),
     #[deku(id = "0x0105")]
     DartRemainingTime(u8),
     #[deku(id = "0x0201")]
-    GameRobotStatus(GameRobotStatus),
+    GameRobotStatus(#[deku(ctx = "version")] GameRobotStatus),
     #[deku(id = "0x0202")]
-    PowerHeatData {
-        chassis_volt: u16,
-        chassis_current: u16,
-        chassis_power: f32,
-        chassis_power_buffer: u16,
-        shooter_id1_17mm_cooling_heat: u16,
-        shooter_id2_17mm_cooling_heat: u16,
-        shooter_id1_42mm_cooling_heat: u16,
-    },
+    PowerHeatData(PowerHeatData),
     #[deku(id = "0x0203")]
-    GameRobotPos {
-        x: f32,
-        y: f32,
-        z: f32,
-        yaw: f32,
-    },
+    GameRobotPos(GameRobotPos),
     #[deku(id = "0x0204")]
-    PowerRuneBuff {
-        /// 机器人血量补血状态
-        #[deku(bits = "1")]
-        robot_hp_restoration_status: bool,
-
-        /// 枪口热量冷却加速
-        #[deku(bits = "1")]
-        barrel_heat_cooling_acceleration: bool,
-
-        /// 机器人防御加成
-        #[deku(bits = "1")]
-        robot_defense_buff: bool,
-
-        /// 机器人攻击加成
-        #[deku(bits = "1")]
-        #[deku(pad_bits_after = "4")]
-        robot_attack_buff: bool,
-    },
+    PowerRuneBuff(PowerRuneBuff),
     #[deku(id = "0x0205")]
     AerialRobotEnergy(u8),
     #[deku(id = "0x0206")]
-    RobotHurt(u8),
+    RobotHurt(RobotHurt),
     #[deku(id = "0x0207")]
-    ShootData([u8; 7]),
+    ShootData(ShootData),
     #[deku(id = "0x0208")]
-    BulletRemaining([u8; 6]),
+    BulletRemaining(BulletRemaining),
     #[deku(id = "0x0209")]
-    RFIDStatus {
-        /// 基地增益点 RFID 状态
-        #[deku(bits = "1")]
-        base_gain_zone: bool,
-        /// 高地增益点 RFID 状态
-        #[deku(bits = "1")]
-        elevated_ground_gain_zone: bool,
-        /// 能量机关激活点 RFID 状态
-        #[deku(bits = "1")]
-        power_rune_activation_point: bool,
-        /// 飞坡增益点 RFID 状态
-        #[deku(bits = "1")]
-        launch_ramp_gain_zone: bool,
-        /// 前哨岗增益点 RFID 状态
-        #[deku(bits = "1")]
-        #[deku(pad_bits_after = "1")]
-        outpost_gain_zone: bool,
-        /// 补血点增益点 RFID 状态
-        #[deku(bits = "1")]
-        restoration_zone_buff: bool,
-        /// 工程机器人复活卡 RFID 状态
-        #[deku(bits = "1")]
-        #[deku(pad_bits_after = "24")]
-        engineer_robot_recovery_card: bool,
-    },
+    RFIDStatus(#[deku(ctx = "version")] RFIDStatus),
     #[deku(id = "0x020A")]
-    DartClientCmd {
-        dart_launch_opening_status: u8,
-        dart_attack_target: u8,
-        target_change_time: u16,
-        latest_launch_cmd_time: u16,
-    },
+    DartClientCmd(DartClientCmd),
     #[deku(id = "0x020B")]
-    GroundRobotPosition {
-        hero_x: f32,
-        hero_y: f32,
-        engineer_x: f32,
-        engineer_y: f32,
-        standard_3_x: f32,
-        standard_3_y: f32,
-        standard_4_x: f32,
-        standard_4_y: f32,
-        standard_5_x: f32,
-        standard_5_y: f32,
-    },
+    GroundRobotPosition(GroundRobotPosition),
     #[deku(id = "0x020C")]
     RadarMarkData(RadarMarkData),
     #[deku(id = "0x0301")]
@@ -167,51 +231,9 @@ pub enum Message {
         Vec<u8>
     ),
     #[deku(id = "0x0303")]
-    MapCommand {
-        target_position: (f32, f32, f32),
-        command_keyboard: u8,
-        target_robot_id: u16,
-    },
+    MapCommand(MapCommand),
     #[deku(id = "0x0304")]
-    RemoteControl {
-        mouse_x: u16,
-        mouse_y: u16,
-        mouse_z: u16,
-        left_button_down: bool,
-        right_button_down: bool,
-        #[deku(bits = "1")]
-        w_key_down: bool,
-        #[deku(bits = "1")]
-        s_key_down: bool,
-        #[deku(bits = "1")]
-        a_key_down: bool,
-        #[deku(bits = "1")]
-        d_key_down: bool,
-        #[deku(bits = "1")]
-        shift_key_down: bool,
-        #[deku(bits = "1")]
-        ctrl_key_down: bool,
-        #[deku(bits = "1")]
-        q_key_down: bool,
-        #[deku(bits = "1")]
-        e_key_down: bool,
-        #[deku(bits = "1")]
-        r_key_down: bool,
-        #[deku(bits = "1")]
-        f_key_down: bool,
-        #[deku(bits = "1")]
-        g_key_down: bool,
-        #[deku(bits = "1")]
-        z_key_down: bool,
-        #[deku(bits = "1")]
-        x_key_down: bool,
-        #[deku(bits = "1")]
-        c_key_down: bool,
-        #[deku(bits = "1")]
-        v_key_down: bool,
-        #[deku(bits = "1")]
-        b_key_down: bool,
-    },
+    RemoteControl(RemoteControl),
     #[deku(id = "0x0305")]
     MinimapReceipt {
         target_robot_id: u16,
@@ -219,14 +241,110 @@ pub enum Message {
     },
     #[deku(id = "0x0306")]
     CustomClientData([u8; 8]),
-    // #[deku(id = "0x0307")]
-    // MapSentryData {
-    //     intention: u8,
-    //     start_position_x: u16,
-    //     start_position_y: u16,
-    //     delta_x: [i8; 49],
-    //     delta_y: [i8; 49],
-    // },
+    #[deku(id = "0x0307")]
+    MapSentryData(MapSentryData),
+    /// 本库尚未收录的命令码，保留原始数据以便原样编码
+    #[deku(id_pat = "_")]
+    Unknown {
+        cmd_id: u16,
+        #[deku(bytes_read = "frame_size - 9")]
+        payload: Vec<u8>,
+    },
+}
+
+impl Message {
+    pub fn cmd_id(&self) -> u16 {
+        match self {
+            Message::Unknown { cmd_id, .. } => *cmd_id,
+            any => any.deku_id().unwrap(),
+        }
+    }
+
+    /// 布局随赛季变化的消息所用的布局版本，见 [`ProtocolVersion::layout`]，其余消息返回 `None`
+    pub fn version(&self) -> Option<ProtocolVersion> {
+        match self {
+            Message::EventData(EventData::V2023(_))
+            | Message::RefereeWarning(RefereeWarning::V2023(_))
+            | Message::GameRobotStatus(GameRobotStatus::V2023(_))
+            | Message::RFIDStatus(RFIDStatus::V2023(_)) => Some(ProtocolVersion::V2023),
+            Message::EventData(EventData::V2024(_))
+            | Message::RefereeWarning(RefereeWarning::V2024(_))
+            | Message::GameRobotStatus(GameRobotStatus::V2024(_))
+            | Message::RFIDStatus(RFIDStatus::V2024(_)) => Some(ProtocolVersion::V2024),
+            _ => None,
+        }
+    }
+
+    /// 帧头中 `data_length` 应填写的值，即编码后去掉命令码的数据段长度
+    pub fn data_length(&self) -> Result<u16, DekuError> {
+        // frame_size 仅在解码时使用
+        let buf = encode_with_ctx(self, (0, self.version().unwrap_or_default()))?;
+        Ok((buf.len() - 2) as u16)
+    }
+
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            Message::GameStatus(_) => MessageKind::GameStatus,
+            Message::GameResult(_) => MessageKind::GameResult,
+            Message::GameRobotHP { .. } => MessageKind::GameRobotHP,
+            Message::EventData(_) => MessageKind::EventData,
+            Message::SupplyProjectileAction(_) => MessageKind::SupplyProjectileAction,
+            Message::RefereeWarning(_) => MessageKind::RefereeWarning,
+            Message::DartRemainingTime(_) => MessageKind::DartRemainingTime,
+            Message::GameRobotStatus(_) => MessageKind::GameRobotStatus,
+            Message::PowerHeatData(_) => MessageKind::PowerHeatData,
+            Message::GameRobotPos(_) => MessageKind::GameRobotPos,
+            Message::PowerRuneBuff(_) => MessageKind::PowerRuneBuff,
+            Message::AerialRobotEnergy(_) => MessageKind::AerialRobotEnergy,
+            Message::RobotHurt(_) => MessageKind::RobotHurt,
+            Message::ShootData(_) => MessageKind::ShootData,
+            Message::BulletRemaining(_) => MessageKind::BulletRemaining,
+            Message::RFIDStatus(_) => MessageKind::RFIDStatus,
+            Message::DartClientCmd(_) => MessageKind::DartClientCmd,
+            Message::GroundRobotPosition(_) => MessageKind::GroundRobotPosition,
+            Message::RadarMarkData(_) => MessageKind::RadarMarkData,
+            Message::StudentInteractiveData(_) => MessageKind::StudentInteractiveData,
+            Message::CustomControllerInteractiveData(_) => MessageKind::CustomControllerInteractiveData,
+            Message::MapCommand(_) => MessageKind::MapCommand,
+            Message::RemoteControl(_) => MessageKind::RemoteControl,
+            Message::MinimapReceipt { .. } => MessageKind::MinimapReceipt,
+            Message::CustomClientData(_) => MessageKind::CustomClientData,
+            Message::MapSentryData(_) => MessageKind::MapSentryData,
+            Message::Unknown { .. } => MessageKind::Unknown,
+        }
+    }
+}
+
+/// [`Message`] 的变体种类，用于按类型过滤
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MessageKind {
+    GameStatus,
+    GameResult,
+    GameRobotHP,
+    EventData,
+    SupplyProjectileAction,
+    RefereeWarning,
+    DartRemainingTime,
+    GameRobotStatus,
+    PowerHeatData,
+    GameRobotPos,
+    PowerRuneBuff,
+    AerialRobotEnergy,
+    RobotHurt,
+    ShootData,
+    BulletRemaining,
+    RFIDStatus,
+    DartClientCmd,
+    GroundRobotPosition,
+    RadarMarkData,
+    StudentInteractiveData,
+    CustomControllerInteractiveData,
+    MapCommand,
+    RemoteControl,
+    MinimapReceipt,
+    CustomClientData,
+    MapSentryData,
+    Unknown,
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
@@ -240,7 +358,7 @@ pub struct GameStatus {
 
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[deku(type = "u8")]
+#[deku(id_type = "u8")]
 #[deku(bits = "4")]
 pub enum GameType {
     #[default]
@@ -260,7 +378,7 @@ pub enum GameType {
 
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[deku(type = "u8")]
+#[deku(id_type = "u8")]
 #[deku(bits = "4")]
 pub enum GameProgress {
     #[default]
@@ -281,7 +399,7 @@ pub enum GameProgress {
 
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[deku(type = "u8")]
+#[deku(id_type = "u8")]
 pub enum Winner {
     #[deku(id = "0")]
     Draw,
@@ -294,67 +412,46 @@ pub enum Winner {
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct TeamHP {
-    _1: u16,
-    _2: u16,
-    _3: u16,
-    _4: u16,
-    _5: u16,
-    _7: u16,
-    outpost: u16,
-    base: u16,
+    pub _1: u16,
+    pub _2: u16,
+    pub _3: u16,
+    pub _4: u16,
+    pub _5: u16,
+    pub _7: u16,
+    pub outpost: u16,
+    pub base: u16,
 }
 
-#[deku_derive(DekuRead, DekuWrite)]
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct EventData {
-    /// 己方补给站 1 号补血点占领状态
-    #[deku(bits = "1")]
-    pub restoration_zone_1_occupied: bool,
-
-    /// 己方补给站 3 号补血点占领状态
-    #[deku(bits = "1")]
-    pub restoration_zone_2_occupied: bool,
-
-    /// 己方补给站 3 号补血点占领状态
-    #[deku(bits = "1")]
-    pub restoration_zone_3_occupied: bool,
-
-    /// 打击点占领状态
-    #[deku(bits = "1")]
-    pub attack_point_occupied: bool,
-
-    /// 小能量机关激活状态
-    #[deku(bits = "1")]
-    pub small_power_rune_activated: bool,
-
-    /// 大能量机关激活状态
-    #[deku(bits = "1")]
-    pub big_power_rune_activated: bool,
-
-    /// 己方侧 R2/B2 环形高地占领状态
-    #[deku(bits = "1")]
-    pub r2b2_occupied: bool,
-
-    /// 己方侧 R3/B3 梯形高地占领状态
-    #[deku(bits = "1")]
-    pub r3b3_occupied: bool,
-
-    #[deku(bits = "1")]
-    pub r4b4_occupied: bool,
-
-    /// 己方基地护盾状态
-    #[deku(bits = "1")]
-    pub base_has_virtual_shield: bool,
+impl TeamHP {
+    /// 对应兵种的血量，无人机、飞镖与雷达没有血量
+    pub fn robot_hp(&self, job: id::RobotJob) -> Option<u16> {
+        match job {
+            id::RobotJob::Hero => Some(self._1),
+            id::RobotJob::Engineer => Some(self._2),
+            id::RobotJob::Infantry3 => Some(self._3),
+            id::RobotJob::Infantry4 => Some(self._4),
+            id::RobotJob::Infantry5 => Some(self._5),
+            id::RobotJob::Sentry => Some(self._7),
+            id::RobotJob::Drone | id::RobotJob::Dart | id::RobotJob::Radar => None,
+        }
+    }
 
-    /// 己方前哨站状态
-    #[deku(bits = "1")]
-    #[deku(pad_bits_after = "21")]
-    pub outpost_survives: bool,
+    pub fn robot_hp_mut(&mut self, job: id::RobotJob) -> Option<&mut u16> {
+        match job {
+            id::RobotJob::Hero => Some(&mut self._1),
+            id::RobotJob::Engineer => Some(&mut self._2),
+            id::RobotJob::Infantry3 => Some(&mut self._3),
+            id::RobotJob::Infantry4 => Some(&mut self._4),
+            id::RobotJob::Infantry5 => Some(&mut self._5),
+            id::RobotJob::Sentry => Some(&mut self._7),
+            id::RobotJob::Drone | id::RobotJob::Dart | id::RobotJob::Radar => None,
+        }
+    }
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[deku(type = "u8")]
+#[deku(id_type = "u8")]
 pub enum ProjectileSupplier {
     #[deku(id = "1")]
     _1,
@@ -364,7 +461,7 @@ pub enum ProjectileSupplier {
 
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[deku(type = "u8")]
+#[deku(id_type = "u8")]
 pub enum ProjectileReloadingRobot {
     #[deku(id = "0")]
     None,
@@ -393,7 +490,7 @@ pub enum ProjectileReloadingRobot {
 
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[deku(type = "u8")]
+#[deku(id_type = "u8")]
 pub enum ProjectileOutletStatus {
     #[deku(id = "0")]
     Closed,
@@ -405,7 +502,7 @@ pub enum ProjectileOutletStatus {
 
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[deku(type = "u8")]
+#[deku(id_type = "u8")]
 pub enum SuppliedProjectileNumber {
     #[deku(id = "50")]
     _50,
@@ -419,45 +516,279 @@ pub enum SuppliedProjectileNumber {
 
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[deku(type = "u8")]
+#[deku(ctx = "version: ProtocolVersion", id = "version")]
+pub enum EventData {
+    #[deku(id = "ProtocolVersion::V2023")]
+    V2023(v2023::EventData),
+    #[deku(id_pat = "ProtocolVersion::V2024 | ProtocolVersion::V2025")]
+    V2024(v2024::EventData),
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[deku(ctx = "version: ProtocolVersion", id = "version")]
 pub enum RefereeWarning {
-    #[deku(id = "1")]
-    YellowCard { foul_robot_id: u8 },
-    #[deku(id = "2")]
-    RedCard { foul_robot_id: u8 },
-    #[deku(id = "3")]
-    Forfeiture(#[deku(pad_bytes_after = "1")] ()),
+    #[deku(id = "ProtocolVersion::V2023")]
+    V2023(v2023::RefereeWarning),
+    #[deku(id_pat = "ProtocolVersion::V2024 | ProtocolVersion::V2025")]
+    V2024(v2024::RefereeWarning),
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[deku(ctx = "version: ProtocolVersion", id = "version")]
+pub enum GameRobotStatus {
+    #[deku(id = "ProtocolVersion::V2023")]
+    V2023(v2023::GameRobotStatus),
+    #[deku(id_pat = "ProtocolVersion::V2024 | ProtocolVersion::V2025")]
+    V2024(v2024::GameRobotStatus),
+}
+
+impl GameRobotStatus {
+    pub fn robot_id(&self) -> u8 {
+        match self {
+            GameRobotStatus::V2023(status) => status.robot_id,
+            GameRobotStatus::V2024(status) => status.robot_id,
+        }
+    }
+    pub fn robot_level(&self) -> u8 {
+        match self {
+            GameRobotStatus::V2023(status) => status.robot_level,
+            GameRobotStatus::V2024(status) => status.robot_level,
+        }
+    }
+    pub fn remain_hp(&self) -> u16 {
+        match self {
+            GameRobotStatus::V2023(status) => status.remain_hp,
+            GameRobotStatus::V2024(status) => status.remain_hp,
+        }
+    }
+    pub fn max_hp(&self) -> u16 {
+        match self {
+            GameRobotStatus::V2023(status) => status.max_hp,
+            GameRobotStatus::V2024(status) => status.max_hp,
+        }
+    }
+    pub fn chassis_power_limit(&self) -> u16 {
+        match self {
+            GameRobotStatus::V2023(status) => status.chassis_power_limit,
+            GameRobotStatus::V2024(status) => status.chassis_power_limit,
+        }
+    }
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[deku(ctx = "version: ProtocolVersion", id = "version")]
+pub enum RFIDStatus {
+    #[deku(id = "ProtocolVersion::V2023")]
+    V2023(v2023::RFIDStatus),
+    #[deku(id_pat = "ProtocolVersion::V2024 | ProtocolVersion::V2025")]
+    V2024(v2024::RFIDStatus),
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupplyProjectileAction {
+    pub supplier: ProjectileSupplier,
+    pub robot: ProjectileReloadingRobot,
+    pub outlet_status: ProjectileOutletStatus,
+    pub supplied_number: SuppliedProjectileNumber,
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct GameRobotStatus {
-    pub robot_id: u8,
-    pub robot_level: u8,
-    pub remain_hp: u16,
-    pub max_hp: u16,
+pub struct PowerHeatData {
+    pub chassis_volt: u16,
+    pub chassis_current: u16,
+    pub chassis_power: f32,
+    pub chassis_power_buffer: u16,
+    pub shooter_id1_17mm_cooling_heat: u16,
+    pub shooter_id2_17mm_cooling_heat: u16,
+    pub shooter_id1_42mm_cooling_heat: u16,
+}
 
-    pub shooter_id1_17mm_cooling_rate: u16,
-    pub shooter_id1_17mm_cooling_limit: u16,
-    pub shooter_id1_17mm_speed_limit: u16,
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GameRobotPos {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub yaw: f32,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PowerRuneBuff {
+    /// 机器人血量补血状态
+    #[deku(bits = "1")]
+    pub robot_hp_restoration_status: bool,
+
+    /// 枪口热量冷却加速
+    #[deku(bits = "1")]
+    pub barrel_heat_cooling_acceleration: bool,
 
-    pub shooter_id2_17mm_cooling_rate: u16,
-    pub shooter_id2_17mm_cooling_limit: u16,
-    pub shooter_id2_17mm_speed_limit: u16,
+    /// 机器人防御加成
+    #[deku(bits = "1")]
+    pub robot_defense_buff: bool,
+
+    /// 机器人攻击加成
+    #[deku(bits = "1")]
+    #[deku(pad_bits_after = "4")]
+    pub robot_attack_buff: bool,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DartClientCmd {
+    pub dart_launch_opening_status: u8,
+    pub dart_attack_target: u8,
+    pub target_change_time: u16,
+    pub latest_launch_cmd_time: u16,
+}
 
-    pub shooter_id1_42mm_cooling_rate: u16,
-    pub shooter_id1_42mm_cooling_limit: u16,
-    pub shooter_id1_42mm_speed_limit: u16,
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GroundRobotPosition {
+    pub hero_x: f32,
+    pub hero_y: f32,
+    pub engineer_x: f32,
+    pub engineer_y: f32,
+    pub standard_3_x: f32,
+    pub standard_3_y: f32,
+    pub standard_4_x: f32,
+    pub standard_4_y: f32,
+    pub standard_5_x: f32,
+    pub standard_5_y: f32,
+}
 
-    pub chassis_power_limit: u16,
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MapCommand {
+    pub target_position: (f32, f32, f32),
+    pub command_keyboard: u8,
+    pub target_robot_id: u16,
+}
 
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RemoteControl {
+    pub mouse_x: u16,
+    pub mouse_y: u16,
+    pub mouse_z: u16,
+    pub left_button_down: bool,
+    pub right_button_down: bool,
+    #[deku(bits = "1")]
+    pub w_key_down: bool,
+    #[deku(bits = "1")]
+    pub s_key_down: bool,
+    #[deku(bits = "1")]
+    pub a_key_down: bool,
+    #[deku(bits = "1")]
+    pub d_key_down: bool,
     #[deku(bits = "1")]
-    pub mains_power_gimbal_output: bool,
+    pub shift_key_down: bool,
     #[deku(bits = "1")]
-    pub mains_power_chassis_output: bool,
+    pub ctrl_key_down: bool,
     #[deku(bits = "1")]
-    #[deku(pad_bits_after = "5")]
-    pub mains_power_shooter_output: bool,
+    pub q_key_down: bool,
+    #[deku(bits = "1")]
+    pub e_key_down: bool,
+    #[deku(bits = "1")]
+    pub r_key_down: bool,
+    #[deku(bits = "1")]
+    pub f_key_down: bool,
+    #[deku(bits = "1")]
+    pub g_key_down: bool,
+    #[deku(bits = "1")]
+    pub z_key_down: bool,
+    #[deku(bits = "1")]
+    pub x_key_down: bool,
+    #[deku(bits = "1")]
+    pub c_key_down: bool,
+    #[deku(bits = "1")]
+    pub v_key_down: bool,
+    #[deku(bits = "1")]
+    pub b_key_down: bool,
+}
+
+/// 伤害来源，高 4 位为血量变化类型，低 4 位为装甲 ID
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RobotHurt {
+    pub hurt_type: HurtType,
+    /// 血量变化类型为装甲伤害或装甲撞击时，代表受击装甲的 ID，否则为 0
+    #[deku(bits = "4")]
+    pub armor_id: u8,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[deku(id_type = "u8")]
+#[deku(bits = "4")]
+pub enum HurtType {
+    /// 装甲被弹丸攻击扣血
+    #[deku(id = "0")]
+    ArmorHit,
+    /// 裁判系统重要模块离线扣血
+    #[deku(id = "1")]
+    ModuleOffline,
+    /// 射击初速度超限扣血
+    #[deku(id = "2")]
+    OverSpeed,
+    /// 枪口热量超限扣血
+    #[deku(id = "3")]
+    OverHeat,
+    /// 底盘功率超限扣血
+    #[deku(id = "4")]
+    OverPower,
+    /// 装甲模块受到撞击扣血
+    #[deku(id = "5")]
+    Collision,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BulletRemaining {
+    /// 17mm 弹丸允许发弹量
+    pub bullet_remaining_num_17mm: u16,
+    /// 42mm 弹丸允许发弹量
+    pub bullet_remaining_num_42mm: u16,
+    /// 剩余金币数量
+    pub coin_remaining_num: u16,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShootData {
+    pub bullet_type: BulletType,
+    pub shooter_id: ShooterId,
+    /// 弹丸射频，单位 Hz
+    pub launching_frequency: u8,
+    /// 弹丸初速度，单位 m/s
+    pub initial_speed: f32,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[deku(id_type = "u8")]
+pub enum BulletType {
+    #[deku(id = "1")]
+    _17mm,
+    #[deku(id = "2")]
+    _42mm,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[deku(id_type = "u8")]
+pub enum ShooterId {
+    #[deku(id = "1")]
+    Shooter1_17mm,
+    #[deku(id = "2")]
+    Shooter2_17mm,
+    #[deku(id = "3")]
+    Shooter1_42mm,
 }
 
 #[deku_derive(DekuRead, DekuWrite)]
@@ -471,6 +802,91 @@ pub struct RadarMarkData {
     pub mark_sentry_progress: u8,
 }
 
+/// 哨兵路径点增量个数
+pub const MAP_SENTRY_DELTA_COUNT: usize = 49;
+
+/// 由哨兵机器人发送给己方操作手，用于在小地图上显示规划路径
+///
+/// 坐标单位为 dm，以小地图左下角为原点。`delta_x`/`delta_y` 依次给出相对于上一个路径点的增量，
+/// 长度必须为 [`MAP_SENTRY_DELTA_COUNT`]，可通过 [`MapSentryData::from_points`] 由绝对坐标构造。
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapSentryData {
+    pub intention: SentryIntention,
+    pub start_position_x: u16,
+    pub start_position_y: u16,
+    #[deku(count = "MAP_SENTRY_DELTA_COUNT", assert = "delta_x.len() == MAP_SENTRY_DELTA_COUNT")]
+    pub delta_x: Vec<i8>,
+    #[deku(count = "MAP_SENTRY_DELTA_COUNT", assert = "delta_y.len() == MAP_SENTRY_DELTA_COUNT")]
+    pub delta_y: Vec<i8>,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[deku(id_type = "u8")]
+pub enum SentryIntention {
+    /// 到目标点攻击
+    #[deku(id = "1")]
+    Attack,
+    /// 到目标点防守
+    #[deku(id = "2")]
+    Defend,
+    /// 移动到目标点
+    #[deku(id = "3")]
+    Move,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum MapSentryPathError {
+    #[error("Path contains no point")]
+    Empty,
+    #[error("Path contains {0} points, at most {} allowed", MAP_SENTRY_DELTA_COUNT + 1)]
+    TooManyPoints(usize),
+    #[error("Delta ({dx}, {dy}) from point {index} to its predecessor is out of range")]
+    DeltaOutOfRange { index: usize, dx: i32, dy: i32 },
+}
+
+impl MapSentryData {
+    /// 由绝对坐标（单位 dm）构造路径，第一个点为起点，之后最多 49 个点，相邻两点各维度的差值须在 `i8` 范围内
+    pub fn from_points(intention: SentryIntention, points: &[(u16, u16)]) -> Result<Self, MapSentryPathError> {
+        let (&(start_position_x, start_position_y), rest) = points.split_first()
+            .ok_or(MapSentryPathError::Empty)?;
+        if rest.len() > MAP_SENTRY_DELTA_COUNT {
+            return Err(MapSentryPathError::TooManyPoints(points.len()));
+        }
+        let mut delta_x = vec![0i8; MAP_SENTRY_DELTA_COUNT];
+        let mut delta_y = vec![0i8; MAP_SENTRY_DELTA_COUNT];
+        for (index, pair) in points.windows(2).enumerate() {
+            let dx = pair[1].0 as i32 - pair[0].0 as i32;
+            let dy = pair[1].1 as i32 - pair[0].1 as i32;
+            match (i8::try_from(dx), i8::try_from(dy)) {
+                (Ok(x), Ok(y)) => {
+                    delta_x[index] = x;
+                    delta_y[index] = y;
+                }
+                _ => return Err(MapSentryPathError::DeltaOutOfRange { index: index + 1, dx, dy }),
+            }
+        }
+        Ok(Self { intention, start_position_x, start_position_y, delta_x, delta_y })
+    }
+
+    /// 还原为绝对坐标，末尾连续的零增量视为未使用的点位，不会出现在结果中
+    pub fn to_points(&self) -> Vec<(u16, u16)> {
+        let used = self.delta_x.iter().zip(&self.delta_y)
+            .rposition(|(&dx, &dy)| dx != 0 || dy != 0)
+            .map_or(0, |last| last + 1);
+        let (mut x, mut y) = (self.start_position_x as i32, self.start_position_y as i32);
+        let mut points = Vec::with_capacity(used + 1);
+        points.push((self.start_position_x, self.start_position_y));
+        for (&dx, &dy) in self.delta_x.iter().zip(&self.delta_y).take(used) {
+            x += dx as i32;
+            y += dy as i32;
+            points.push((x.clamp(0, u16::MAX as i32) as u16, y.clamp(0, u16::MAX as i32) as u16));
+        }
+        points
+    }
+}
+
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[deku(ctx = "frame_size: u16")]
@@ -494,16 +910,17 @@ impl StudentInteractiveData {
 
 #[deku_derive(DekuRead, DekuWrite)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
-// #[deku(type = "u16")]
+// #[deku(id_type = "u16")]
 #[deku(ctx = "content_id: u16, frame_size: u16", id = "content_id")]
 #[serde(untagged)]
 pub enum StudentInteractiveDataType {
     #[deku(id_pat = "0x0200..=0x02FF")]
     PeerToPeerCommunication {
-        #[deku(skip, default = "content_id")] // TODO: remove work-around
-        content_id: u16,
+        // id_pat 变体的第一个字段在编码时会被当作 id 写出，content_id 放在第一个会被重复编码
         #[deku(bytes_read = "frame_size - 9 - 6")]
         content: Vec<u8>,
+        #[deku(skip, default = "content_id")] // TODO: remove work-around
+        content_id: u16,
     },
     #[deku(id = "0x0100")]
     GraphicDelete {
@@ -0,0 +1,115 @@
+//! 与 IO 无关的增量帧解析，串口、网络或测试数据均可逐段喂入
+
+use bytes::{Buf, BytesMut};
+
+use super::{Frame2, FrameError, ProtocolVersion, HEADER_SIZE, SOF};
+
+/// 解析过程中丢弃的字节及原因
+#[derive(thiserror::Error, Debug)]
+pub enum Diagnostic {
+    #[error("Skipped {} bytes before 0xA5: {}", .0.len(), hex::encode(.0))]
+    Skipped(Vec<u8>),
+    #[error("Wrong CRC8: {} summed to {calculated:#04x}", hex::encode(.header))]
+    Crc8Mismatch { header: [u8; HEADER_SIZE], calculated: u8 },
+    #[error("Invalid data length in header {}", hex::encode(.header))]
+    DataLengthTooLarge { header: [u8; HEADER_SIZE] },
+    #[error("Wrong CRC16: {} summed to {calculated:#06x}", hex::encode(.frame))]
+    Crc16Mismatch { frame: Vec<u8>, calculated: u16 },
+    #[error("Undecodable frame {}: {error}", hex::encode(.frame))]
+    Decode { frame: Vec<u8>, error: FrameError },
+}
+
+#[derive(Debug)]
+pub enum Parsed {
+    Frame(Frame2),
+    Diagnostic(Diagnostic),
+}
+
+/// 增量帧解析器
+///
+/// 通过 [`FrameParser::push`] 喂入任意长度的字节后迭代取出结果，也可以用 [`FrameParser::parse_from`] 直接解析外部缓冲区。
+/// 帧头校验失败时只丢弃 SOF 并重新寻找下一个 0xA5，CRC 均通过但无法解码的帧则整帧丢弃。
+#[derive(Debug, Default)]
+pub struct FrameParser {
+    version: ProtocolVersion,
+    buf: BytesMut,
+}
+
+impl FrameParser {
+    pub fn new(version: ProtocolVersion) -> Self {
+        Self { version, buf: BytesMut::new() }
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// 已缓存但尚未构成完整帧的字节数
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// 从 `buf` 头部解析出一个结果并移除对应字节，数据不足时返回 `None`
+    pub fn parse_from(&self, buf: &mut BytesMut) -> Option<Parsed> {
+        if buf.is_empty() { return None; }
+        if buf[0] != SOF {
+            let skip = buf.iter().position(|&b| b == SOF).unwrap_or(buf.len());
+            let skipped = buf.split_to(skip).to_vec();
+            return Some(Parsed::Diagnostic(Diagnostic::Skipped(skipped)));
+        }
+
+        let size = match Frame2::wire_size(buf) {
+            Ok(size) => size,
+            Err(FrameError::Incomplete { .. }) => return None,
+            Err(FrameError::Crc8Mismatch { calculated, .. }) => {
+                let header = header_of(buf);
+                buf.advance(1);
+                return Some(Parsed::Diagnostic(Diagnostic::Crc8Mismatch { header, calculated }));
+            }
+            Err(_) => {
+                // SOF 已对齐，只剩长度超限一种可能
+                let header = header_of(buf);
+                buf.advance(1);
+                return Some(Parsed::Diagnostic(Diagnostic::DataLengthTooLarge { header }));
+            }
+        };
+        if buf.len() < size { return None; }
+
+        match Frame2::decode_with_version(&buf[..size], self.version) {
+            Ok(frame) => {
+                buf.advance(size);
+                Some(Parsed::Frame(frame))
+            }
+            Err(FrameError::Crc16Mismatch { calculated, .. }) => {
+                let frame = buf[..size].to_vec();
+                buf.advance(1);
+                Some(Parsed::Diagnostic(Diagnostic::Crc16Mismatch { frame, calculated }))
+            }
+            Err(error) => {
+                let frame = buf.split_to(size).to_vec();
+                Some(Parsed::Diagnostic(Diagnostic::Decode { frame, error }))
+            }
+        }
+    }
+}
+
+impl Iterator for FrameParser {
+    type Item = Parsed;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = std::mem::take(&mut self.buf);
+        let parsed = self.parse_from(&mut buf);
+        self.buf = buf;
+        parsed
+    }
+}
+
+fn header_of(buf: &[u8]) -> [u8; HEADER_SIZE] {
+    let mut header = [0u8; HEADER_SIZE];
+    header.copy_from_slice(&buf[..HEADER_SIZE]);
+    header
+}
@@ -1,6 +1,22 @@
-use deku::bitvec::{BitView, Msb0};
+use std::io::Cursor;
+
 use super::*;
 
+/// 以给定 ctx 解码，返回未读取的字节数与解码结果
+fn read<'a, T: DekuReader<'a, Ctx>, Ctx>(data: &'a [u8], ctx: Ctx) -> (usize, T) {
+    let mut reader = Reader::new(Cursor::new(data));
+    let parsed = T::from_reader_with_ctx(&mut reader, ctx).unwrap();
+    (data.len() - reader.bits_read / 8, parsed)
+}
+
+fn write<T: DekuWriter<Ctx>, Ctx>(value: &T, ctx: Ctx) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut writer = Writer::new(Cursor::new(&mut buf));
+    value.to_writer(&mut writer, ctx).unwrap();
+    writer.finalize().unwrap();
+    buf
+}
+
 #[test]
 fn graphic_data_size() {
     let mut data = [0u8; 15];
@@ -18,7 +34,7 @@ fn referee_warning_size() {
     let mut data = [0u8; 2];
     for i in 0b01..=0b11 {
         data[0] = i;
-        let ((_, rest_byte_size), parsed) = RefereeWarning::from_bytes((&data[..], 0)).unwrap();
+        let ((_, rest_byte_size), parsed) = v2023::RefereeWarning::from_bytes((&data[..], 0)).unwrap();
         assert_eq!(rest_byte_size, 0);
         let bytes: Vec<u8> = parsed.try_into().unwrap();
         assert_eq!(bytes.as_slice(), data);
@@ -35,9 +51,8 @@ fn student_interactive_data_size() {
         [data[2], data[3]] = CONTENT_ID.to_le_bytes();
         [data[4], data[5]] = 0x1234_u16.to_le_bytes();
         [data[6], data[7]] = 0x5678_u16.to_le_bytes();
-        let (rest_bits, data) = Message::read(
-            data.view_bits::<Msb0>(), 9 + 6 + custom_size).unwrap();
-        assert_eq!(rest_bits.len(), 0);
+        let (rest, data): (_, Message) = read(&data, (9 + 6 + custom_size, ProtocolVersion::V2023));
+        assert_eq!(rest, 0);
         assert!(matches!(data,
             Message::StudentInteractiveData(
                 StudentInteractiveData {
@@ -48,9 +63,8 @@ fn student_interactive_data_size() {
     for custom_size in 1..=113 {
         data.resize((custom_size) as usize, 0);
         const CONTENT_ID: u16 = 0x0200;
-        let (rest_bits, data) = StudentInteractiveDataType::read(
-            data.view_bits::<Msb0>(), (CONTENT_ID, 9 + 6 + custom_size)).unwrap();
-        assert_eq!(rest_bits.len(), 0);
+        let (rest, data): (_, StudentInteractiveDataType) = read(&data, (CONTENT_ID, 9 + 6 + custom_size));
+        assert_eq!(rest, 0);
         assert!(matches!(data, StudentInteractiveDataType::PeerToPeerCommunication { content_id: CONTENT_ID, .. }));
     }
 }
@@ -61,8 +75,8 @@ fn custom_controller_interactive_size() {
     for custom_size in 1..=30 {
         data.resize((2 + custom_size) as usize, 0);
         [data[0], data[1]] = 0x0302_u16.to_le_bytes();
-        let (rest_bits, _) = Message::read(data.view_bits::<Msb0>(), 9 + custom_size).unwrap();
-        assert_eq!(rest_bits.len(), 0);
+        let (rest, _): (_, Message) = read(&data, (9 + custom_size, ProtocolVersion::V2023));
+        assert_eq!(rest, 0);
     }
 }
 
@@ -74,4 +88,222 @@ fn frame_parse() {
     ];
     let ((_, rest_byte_size), parsed) = Frame2::from_bytes((&data[..], 0)).unwrap();
     assert_eq!(rest_byte_size, 0);
+    // 命令码按小端编码，0x03 0x02 为 0x0203
+    assert!(matches!(parsed.message, Message::GameRobotPos(_)));
+}
+
+/// `Frame2` 向 `Message` 传入的是整帧长度 `data_length + 9`，而不是 `data_length`，否则变长消息会少读 9 个字节
+#[test]
+fn frame_size_ctx_regression() {
+    for length in [1, 9, 10, 112] {
+        let message = Message::StudentInteractiveData(StudentInteractiveData {
+            content_id: 0x0201,
+            send_id: 3,
+            receive_id: 4,
+            content: StudentInteractiveDataType::PeerToPeerCommunication { content_id: 0x0201, content: vec![0x5A; length] },
+        });
+        let bytes = Frame2::encode(1, message).unwrap();
+        assert_eq!(bytes.len(), FRAME_OVERHEAD + 6 + length);
+        let ((_, rest_byte_size), parsed) = Frame2::from_bytes((&bytes[..], 0)).unwrap();
+        assert_eq!(rest_byte_size, 0);
+        assert!(matches!(
+            parsed.message,
+            Message::StudentInteractiveData(StudentInteractiveData { content: StudentInteractiveDataType::PeerToPeerCommunication { ref content, .. }, .. }) if content.len() == length
+        ));
+        assert_eq!(parsed.to_wire_bytes().unwrap(), bytes);
+    }
+}
+
+#[test]
+fn unknown_message_round_trip() {
+    let data = [0x01, 0x0F, 0xDE, 0xAD, 0xBE];
+    let (rest, parsed): (_, Message) = read(&data, (9 + 3, ProtocolVersion::V2023));
+    assert_eq!(rest, 0);
+    assert!(matches!(parsed, Message::Unknown { cmd_id: 0x0F01, ref payload } if payload == &[0xDE, 0xAD, 0xBE]));
+    assert_eq!(write(&parsed, (9 + 3, ProtocolVersion::V2023)), data);
+}
+
+#[test]
+fn map_sentry_data_round_trip() {
+    let points = [(100, 100), (110, 95), (237, 95), (237, 0)];
+    let sentry = MapSentryData::from_points(SentryIntention::Move, &points).unwrap();
+    assert_eq!(sentry.to_points(), points);
+
+    let bytes = write(&Message::MapSentryData(sentry), (9 + 103, ProtocolVersion::V2023));
+    assert_eq!(bytes.len(), 2 + 103);
+    let (rest, data): (_, Message) = read(&bytes, (9 + 103, ProtocolVersion::V2023));
+    assert_eq!(rest, 0);
+    assert!(matches!(data, Message::MapSentryData(MapSentryData { start_position_x: 100, .. })));
+    assert_eq!(data.data_length().unwrap(), 103);
+}
+
+#[test]
+fn map_sentry_data_limits() {
+    assert_eq!(MapSentryData::from_points(SentryIntention::Attack, &[]).unwrap_err(),
+               MapSentryPathError::Empty);
+    assert_eq!(MapSentryData::from_points(SentryIntention::Attack, &[(0, 0); 51]).unwrap_err(),
+               MapSentryPathError::TooManyPoints(51));
+    assert_eq!(MapSentryData::from_points(SentryIntention::Attack, &[(0, 0), (0, 10), (200, 10)]).unwrap_err(),
+               MapSentryPathError::DeltaOutOfRange { index: 2, dx: 200, dy: 0 });
+}
+
+#[test]
+fn shoot_data_parse() {
+    let mut data = vec![0x07, 0x02, 0x01, 0x02, 0x0F];
+    data.extend_from_slice(&15.5f32.to_le_bytes());
+    let (rest, data): (_, Message) = read(&data, (9 + 7, ProtocolVersion::V2023));
+    assert_eq!(rest, 0);
+    let Message::ShootData(shoot) = data else { panic!("expected ShootData, got {:?}", data) };
+    assert_eq!(shoot.bullet_type, BulletType::_17mm);
+    assert_eq!(shoot.shooter_id, ShooterId::Shooter2_17mm);
+    assert_eq!(shoot.launching_frequency, 15);
+    assert_eq!(shoot.initial_speed, 15.5);
+}
+
+#[test]
+fn bullet_remaining_round_trip() {
+    let data = [0x08, 0x02, 0x90, 0x01, 0x0A, 0x00, 0xE8, 0x03];
+    let (rest, parsed): (_, Message) = read(&data, (9 + 6, ProtocolVersion::V2023));
+    assert_eq!(rest, 0);
+    let Message::BulletRemaining(remaining) = &parsed else { panic!("expected BulletRemaining, got {:?}", parsed) };
+    assert_eq!(remaining.bullet_remaining_num_17mm, 400);
+    assert_eq!(remaining.bullet_remaining_num_42mm, 10);
+    assert_eq!(remaining.coin_remaining_num, 1000);
+    assert_eq!(parsed.data_length().unwrap(), 6);
+    assert_eq!(write(&parsed, (9 + 6, ProtocolVersion::V2023)), data);
+}
+
+#[test]
+fn robot_hurt_parse() {
+    let data = [0x06, 0x02, 0x32];
+    let (rest, data): (_, Message) = read(&data, (9 + 1, ProtocolVersion::V2023));
+    assert_eq!(rest, 0);
+    let Message::RobotHurt(hurt) = data else { panic!("expected RobotHurt, got {:?}", data) };
+    assert_eq!(hurt.hurt_type, HurtType::OverHeat);
+    assert_eq!(hurt.armor_id, 2);
+    let bytes: Vec<u8> = hurt.try_into().unwrap();
+    assert_eq!(bytes, [0x32]);
+}
+
+#[test]
+fn game_robot_status_versions() {
+    let mut data = vec![0x01, 0x02, 0x03, 0x01, 150, 0x00, 200, 0x00];
+    data.extend_from_slice(&[40, 0, 240, 0, 100, 0, 0b1110_0000]);
+    let (rest, parsed): (_, Message) = read(&data, (9 + 13, ProtocolVersion::V2024));
+    assert_eq!(rest, 0);
+    let Message::GameRobotStatus(GameRobotStatus::V2024(status)) = parsed else {
+        panic!("expected 2024 GameRobotStatus, got {:?}", parsed)
+    };
+    assert_eq!(status.robot_id, 3);
+    assert_eq!(status.robot_level, 1);
+    assert_eq!(status.shooter_barrel_heat_limit, 240);
+    assert_eq!(status.chassis_power_limit, 100);
+
+    let (rest, parsed): (_, Message) = read(&data, (9 + 13, ProtocolVersion::V2025));
+    assert_eq!(rest, 0);
+    assert!(matches!(parsed, Message::GameRobotStatus(GameRobotStatus::V2024(_))));
+    assert_eq!(parsed.version().unwrap(), ProtocolVersion::V2025.layout());
+
+    data.resize(2 + 27, 0);
+    let (rest, parsed): (_, Message) = read(&data, (9 + 27, ProtocolVersion::V2023));
+    assert_eq!(rest, 0);
+    assert!(matches!(parsed, Message::GameRobotStatus(GameRobotStatus::V2023(_))));
+}
+
+#[test]
+fn frame_data_length() {
+    let message = Message::MinimapReceipt { target_robot_id: 101, target_position: (14.0, 7.5) };
+    assert_eq!(message.data_length().unwrap(), 10);
+    let frame = Frame2::new(0, message).unwrap();
+    let bytes = frame.to_bytes().unwrap();
+    assert_eq!(bytes.len(), 9 + 10);
+}
+
+#[test]
+fn frame_encode_decode() {
+    let message = Message::MinimapReceipt { target_robot_id: 101, target_position: (14.0, 7.5) };
+    let mut bytes = Frame2::encode(42, message).unwrap();
+    let frame = Frame2::decode(&bytes).unwrap();
+    assert_eq!(frame.seq, 42);
+    assert!(matches!(frame.message, Message::MinimapReceipt { target_robot_id: 101, .. }));
+
+    assert!(matches!(Frame2::decode(&bytes[..bytes.len() - 1]), Err(FrameError::Incomplete { needed: 1 })));
+    bytes[10] ^= 0xFF;
+    assert!(matches!(Frame2::decode(&bytes), Err(FrameError::Crc16Mismatch { .. })));
+    bytes[1] ^= 0xFF;
+    assert!(matches!(Frame2::decode(&bytes), Err(FrameError::Crc8Mismatch { .. })));
+}
+
+#[test]
+fn frame_parser_resync() {
+    let message = Message::MinimapReceipt { target_robot_id: 101, target_position: (14.0, 7.5) };
+    let frame = Frame2::encode(1, message).unwrap();
+    let mut corrupted = frame.clone();
+    corrupted[10] ^= 0xFF;
+
+    let mut stream = vec![0x00, 0x11];
+    stream.extend_from_slice(&corrupted);
+    stream.extend_from_slice(&frame);
+
+    let mut parser = parser::FrameParser::new(ProtocolVersion::V2023);
+    let mut events = Vec::new();
+    for chunk in stream.chunks(3) {
+        parser.push(chunk);
+        events.extend(&mut parser);
+    }
+    assert_eq!(parser.buffered(), 0);
+    assert!(matches!(events[0], parser::Parsed::Diagnostic(parser::Diagnostic::Skipped(ref skipped)) if skipped == &[0x00, 0x11]));
+    assert!(matches!(events[1], parser::Parsed::Diagnostic(parser::Diagnostic::Crc16Mismatch { .. })));
+    assert!(matches!(events.last(), Some(parser::Parsed::Frame(Frame2 { seq: 1, .. }))));
+    assert_eq!(events.iter().filter(|event| matches!(event, parser::Parsed::Frame(_))).count(), 1);
+}
+
+#[test]
+fn graphic_builder() {
+    use graphic::{GraphicColor, GraphicData, GraphicEnum, GraphicError};
+
+    let line = GraphicData::line(b"aim", 1, GraphicColor::Green)
+        .from(960, 400).to(960, 680).width(2)
+        .build().unwrap();
+    assert_eq!(line.graphic_type, line.graphic_data.graphic_type());
+    let bytes: Vec<u8> = line.clone().try_into().unwrap();
+    let (_, parsed) = GraphicData::from_bytes((&bytes[..], 0)).unwrap();
+    assert!(matches!(parsed.graphic_data, GraphicEnum::StraightLine(data) if data.end_y == 680 && data.width == 2));
+
+    let number = GraphicData::floating_number("hp", 2, GraphicColor::White).value(1.5).build().unwrap();
+    assert_eq!(number.graphic_name, *b"hp\0");
+    assert_eq!(number.graphic_type, 5);
+    assert!(matches!(number.graphic_data, GraphicEnum::FloatingNumber(data) if data.value == 1500));
+
+    assert_eq!(
+        GraphicData::circle(b"c", 0, GraphicColor::Cyan).center(2048, 0).build().unwrap_err(),
+        GraphicError::OutOfRange { field: "x", value: 2048, max: 2047 },
+    );
+    assert_eq!(GraphicData::arc(b"a", 10, GraphicColor::Pink).build().unwrap_err(), GraphicError::InvalidLayer(10));
+    assert_eq!(GraphicData::rectangle(b"long", 0, GraphicColor::Black).build().unwrap_err(), GraphicError::InvalidName(4));
+
+    let mut mismatch = line;
+    mismatch.graphic_type = 1;
+    assert_eq!(mismatch.validate(), Err(GraphicError::TypeMismatch { graphic_type: 1, expected: 0 }));
+}
+
+#[test]
+fn event_data_v2024_bit_order() {
+    // 前补血点占领、环形高地被己方占领、护盾 100%、飞镖 300 s 时击中基地固定目标
+    let data = [0x01, 0x01, 0x41, 0x40, 0x66, 0x29];
+    let (rest, parsed): (_, Message) = read(&data, (9 + 4, ProtocolVersion::V2024));
+    assert_eq!(rest, 0);
+    let Message::EventData(EventData::V2024(event)) = &parsed else {
+        panic!("expected 2024 EventData, got {:?}", parsed)
+    };
+    assert_eq!(event, &v2024::EventData {
+        restoration_zone_front_occupied: true,
+        ring_highland_occupation: 1,
+        base_virtual_shield_percentage: 100,
+        dart_last_hit_time: 300,
+        dart_last_hit_target: 2,
+        ..Default::default()
+    });
+
+    assert_eq!(write(&parsed, (9 + 4, ProtocolVersion::V2024)), data);
 }
@@ -0,0 +1,123 @@
+//! 2023 赛季裁判系统串口协议中与后续赛季布局不同的数据结构
+
+use deku::prelude::*;
+use serde::{Serialize, Deserialize};
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct EventData {
+    /// 己方补给站 1 号补血点占领状态
+    #[deku(bits = "1")]
+    pub restoration_zone_1_occupied: bool,
+
+    /// 己方补给站 3 号补血点占领状态
+    #[deku(bits = "1")]
+    pub restoration_zone_2_occupied: bool,
+
+    /// 己方补给站 3 号补血点占领状态
+    #[deku(bits = "1")]
+    pub restoration_zone_3_occupied: bool,
+
+    /// 打击点占领状态
+    #[deku(bits = "1")]
+    pub attack_point_occupied: bool,
+
+    /// 小能量机关激活状态
+    #[deku(bits = "1")]
+    pub small_power_rune_activated: bool,
+
+    /// 大能量机关激活状态
+    #[deku(bits = "1")]
+    pub big_power_rune_activated: bool,
+
+    /// 己方侧 R2/B2 环形高地占领状态
+    #[deku(bits = "1")]
+    pub r2b2_occupied: bool,
+
+    /// 己方侧 R3/B3 梯形高地占领状态
+    #[deku(bits = "1")]
+    pub r3b3_occupied: bool,
+
+    #[deku(bits = "1")]
+    pub r4b4_occupied: bool,
+
+    /// 己方基地护盾状态
+    #[deku(bits = "1")]
+    pub base_has_virtual_shield: bool,
+
+    /// 己方前哨站状态
+    #[deku(bits = "1")]
+    #[deku(pad_bits_after = "21")]
+    pub outpost_survives: bool,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[deku(id_type = "u8")]
+pub enum RefereeWarning {
+    #[deku(id = "1")]
+    YellowCard { foul_robot_id: u8 },
+    #[deku(id = "2")]
+    RedCard { foul_robot_id: u8 },
+    #[deku(id = "3")]
+    Forfeiture(#[deku(pad_bytes_after = "1")] ()),
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GameRobotStatus {
+    pub robot_id: u8,
+    pub robot_level: u8,
+    pub remain_hp: u16,
+    pub max_hp: u16,
+
+    pub shooter_id1_17mm_cooling_rate: u16,
+    pub shooter_id1_17mm_cooling_limit: u16,
+    pub shooter_id1_17mm_speed_limit: u16,
+
+    pub shooter_id2_17mm_cooling_rate: u16,
+    pub shooter_id2_17mm_cooling_limit: u16,
+    pub shooter_id2_17mm_speed_limit: u16,
+
+    pub shooter_id1_42mm_cooling_rate: u16,
+    pub shooter_id1_42mm_cooling_limit: u16,
+    pub shooter_id1_42mm_speed_limit: u16,
+
+    pub chassis_power_limit: u16,
+
+    #[deku(bits = "1")]
+    pub mains_power_gimbal_output: bool,
+    #[deku(bits = "1")]
+    pub mains_power_chassis_output: bool,
+    #[deku(bits = "1")]
+    #[deku(pad_bits_after = "5")]
+    pub mains_power_shooter_output: bool,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RFIDStatus {
+    /// 基地增益点 RFID 状态
+    #[deku(bits = "1")]
+    pub base_gain_zone: bool,
+    /// 高地增益点 RFID 状态
+    #[deku(bits = "1")]
+    pub elevated_ground_gain_zone: bool,
+    /// 能量机关激活点 RFID 状态
+    #[deku(bits = "1")]
+    pub power_rune_activation_point: bool,
+    /// 飞坡增益点 RFID 状态
+    #[deku(bits = "1")]
+    pub launch_ramp_gain_zone: bool,
+    /// 前哨岗增益点 RFID 状态
+    #[deku(bits = "1")]
+    #[deku(pad_bits_after = "1")]
+    pub outpost_gain_zone: bool,
+    /// 补血点增益点 RFID 状态
+    #[deku(bits = "1")]
+    pub restoration_zone_buff: bool,
+    /// 工程机器人复活卡 RFID 状态
+    #[deku(bits = "1")]
+    #[deku(pad_bits_after = "24")]
+    pub engineer_robot_recovery_card: bool,
+}
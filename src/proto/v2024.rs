@@ -0,0 +1,219 @@
+//! 2024 赛季起裁判系统串口协议中布局发生变化的数据结构，[`ProtocolVersion::V2025`](super::ProtocolVersion::V2025) 暂沿用
+
+use deku::ctx::Endian;
+use deku::no_std_io::{Read, Seek, Write};
+use deku::prelude::*;
+use serde::{Serialize, Deserialize};
+
+/// 事件数据
+///
+/// 各字段按从低位到高位的顺序打包在一个小端 `u32` 中，护盾与飞镖时间跨越字节边界，
+/// 不能按 deku 默认的高位在前解析，因此手动实现编解码。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct EventData {
+    /// 己方补给站前补血点占领状态
+    pub restoration_zone_front_occupied: bool,
+
+    /// 己方补给站内部补血点占领状态
+    pub restoration_zone_inside_occupied: bool,
+
+    /// 己方补给区占领状态（仅 RMUL 适用）
+    pub supply_zone_occupied: bool,
+
+    /// 己方能量机关激活点占领状态
+    pub power_rune_activation_point_occupied: bool,
+
+    /// 己方小能量机关激活状态
+    pub small_power_rune_activated: bool,
+
+    /// 己方大能量机关激活状态
+    pub big_power_rune_activated: bool,
+
+    /// 己方环形高地占领状态，1 为被己方占领，2 为被对方占领
+    pub ring_highland_occupation: u8,
+
+    /// 己方 R3/B3 梯形高地占领状态，1 为被己方占领，2 为被对方占领
+    pub r3b3_occupation: u8,
+
+    /// 己方 R4/B4 梯形高地占领状态，1 为被己方占领，2 为被对方占领
+    pub r4b4_occupation: u8,
+
+    /// 己方基地虚拟护盾剩余值百分比，7 位
+    pub base_virtual_shield_percentage: u8,
+
+    /// 飞镖最后一次击中己方前哨站或基地的时间，单位 s，9 位
+    pub dart_last_hit_time: u16,
+
+    /// 飞镖最后一次击中己方前哨站或基地的具体目标，1 为前哨站，2 为基地固定目标，3 为基地随机目标
+    pub dart_last_hit_target: u8,
+
+    /// 中心增益点占领情况（仅 RMUL 适用）
+    pub central_buff_occupation: u8,
+}
+
+impl EventData {
+    pub fn from_bits(bits: u32) -> Self {
+        let field = |offset: u32, width: u32| (bits >> offset) & ((1 << width) - 1);
+        Self {
+            restoration_zone_front_occupied: field(0, 1) != 0,
+            restoration_zone_inside_occupied: field(1, 1) != 0,
+            supply_zone_occupied: field(2, 1) != 0,
+            power_rune_activation_point_occupied: field(3, 1) != 0,
+            small_power_rune_activated: field(4, 1) != 0,
+            big_power_rune_activated: field(5, 1) != 0,
+            ring_highland_occupation: field(6, 2) as u8,
+            r3b3_occupation: field(8, 2) as u8,
+            r4b4_occupation: field(10, 2) as u8,
+            base_virtual_shield_percentage: field(12, 7) as u8,
+            dart_last_hit_time: field(19, 9) as u16,
+            dart_last_hit_target: field(28, 2) as u8,
+            central_buff_occupation: field(30, 2) as u8,
+        }
+    }
+
+    /// 超出位宽的值被截断
+    pub fn to_bits(&self) -> u32 {
+        let field = |value: u32, offset: u32, width: u32| (value & ((1 << width) - 1)) << offset;
+        field(self.restoration_zone_front_occupied as u32, 0, 1)
+            | field(self.restoration_zone_inside_occupied as u32, 1, 1)
+            | field(self.supply_zone_occupied as u32, 2, 1)
+            | field(self.power_rune_activation_point_occupied as u32, 3, 1)
+            | field(self.small_power_rune_activated as u32, 4, 1)
+            | field(self.big_power_rune_activated as u32, 5, 1)
+            | field(self.ring_highland_occupation as u32, 6, 2)
+            | field(self.r3b3_occupation as u32, 8, 2)
+            | field(self.r4b4_occupation as u32, 10, 2)
+            | field(self.base_virtual_shield_percentage as u32, 12, 7)
+            | field(self.dart_last_hit_time as u32, 19, 9)
+            | field(self.dart_last_hit_target as u32, 28, 2)
+            | field(self.central_buff_occupation as u32, 30, 2)
+    }
+}
+
+impl<'a> DekuReader<'a> for EventData {
+    fn from_reader_with_ctx<R: Read + Seek>(reader: &mut Reader<R>, _: ()) -> Result<Self, DekuError> {
+        u32::from_reader_with_ctx(reader, Endian::Little).map(Self::from_bits)
+    }
+}
+
+impl DekuWriter for EventData {
+    fn to_writer<W: Write + Seek>(&self, writer: &mut Writer<W>, _: ()) -> Result<(), DekuError> {
+        self.to_bits().to_writer(writer, Endian::Little)
+    }
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefereeWarning {
+    pub level: PenaltyLevel,
+    /// 违规机器人 ID，判负和双方黄牌时为 0
+    pub offending_robot_id: u8,
+    /// 违规机器人对应判罚等级的违规次数
+    pub count: u8,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[deku(id_type = "u8")]
+pub enum PenaltyLevel {
+    #[deku(id = "1")]
+    BothYellowCard,
+    #[deku(id = "2")]
+    YellowCard,
+    #[deku(id = "3")]
+    RedCard,
+    #[deku(id = "4")]
+    Forfeiture,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GameRobotStatus {
+    pub robot_id: u8,
+    pub robot_level: u8,
+    pub remain_hp: u16,
+    pub max_hp: u16,
+
+    /// 机器人射击热量每秒冷却值
+    pub shooter_barrel_cooling_value: u16,
+    /// 机器人射击热量上限
+    pub shooter_barrel_heat_limit: u16,
+
+    pub chassis_power_limit: u16,
+
+    #[deku(bits = "1")]
+    pub mains_power_gimbal_output: bool,
+    #[deku(bits = "1")]
+    pub mains_power_chassis_output: bool,
+    #[deku(bits = "1")]
+    #[deku(pad_bits_after = "5")]
+    pub mains_power_shooter_output: bool,
+}
+
+#[deku_derive(DekuRead, DekuWrite)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RFIDStatus {
+    /// 己方基地增益点
+    #[deku(bits = "1")]
+    pub base_gain_zone: bool,
+    /// 己方环形高地增益点
+    #[deku(bits = "1")]
+    pub own_ring_highland: bool,
+    /// 对方环形高地增益点
+    #[deku(bits = "1")]
+    pub enemy_ring_highland: bool,
+    /// 己方 R3/B3 梯形高地增益点
+    #[deku(bits = "1")]
+    pub own_r3b3_highland: bool,
+    /// 对方 R3/B3 梯形高地增益点
+    #[deku(bits = "1")]
+    pub enemy_r3b3_highland: bool,
+    /// 己方 R4/B4 梯形高地增益点
+    #[deku(bits = "1")]
+    pub own_r4b4_highland: bool,
+    /// 对方 R4/B4 梯形高地增益点
+    #[deku(bits = "1")]
+    pub enemy_r4b4_highland: bool,
+    /// 己方能量机关激活点
+    #[deku(bits = "1")]
+    pub power_rune_activation_point: bool,
+    /// 己方飞坡增益点（靠近己方一侧飞坡前）
+    #[deku(bits = "1")]
+    pub own_launch_ramp_front: bool,
+    /// 己方飞坡增益点（靠近己方一侧飞坡后）
+    #[deku(bits = "1")]
+    pub own_launch_ramp_back: bool,
+    /// 对方飞坡增益点（靠近对方一侧飞坡前）
+    #[deku(bits = "1")]
+    pub enemy_launch_ramp_front: bool,
+    /// 对方飞坡增益点（靠近对方一侧飞坡后）
+    #[deku(bits = "1")]
+    pub enemy_launch_ramp_back: bool,
+    /// 己方前哨站增益点
+    #[deku(bits = "1")]
+    pub outpost_gain_zone: bool,
+    /// 己方补血点
+    #[deku(bits = "1")]
+    pub restoration_zone: bool,
+    /// 己方哨兵巡逻区
+    #[deku(bits = "1")]
+    pub own_sentry_patrol_zone: bool,
+    /// 对方哨兵巡逻区
+    #[deku(bits = "1")]
+    pub enemy_sentry_patrol_zone: bool,
+    /// 己方大资源岛增益点
+    #[deku(bits = "1")]
+    pub own_big_resource_island: bool,
+    /// 对方大资源岛增益点
+    #[deku(bits = "1")]
+    pub enemy_big_resource_island: bool,
+    /// 己方兑换区
+    #[deku(bits = "1")]
+    pub exchange_zone: bool,
+    /// 中心增益点（仅 RMUL 适用）
+    #[deku(bits = "1")]
+    pub central_buff_point: bool,
+    /// 2024 赛季的保留位，2025 赛季起陆续用于新增的增益点位
+    #[deku(bits = "12")]
+    pub reserved: u16,
+}
@@ -0,0 +1,302 @@
+//! 带时间戳的原始裁判系统数据录制与回放
+//!
+//! 录制文件为纯文本，每行一条记录，格式为 `<距录制开始的微秒数> <十六进制数据>`，
+//! 既可以记录串口读到的原始字节（包括损坏的数据），也可以记录解析后的帧。
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::proto;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RecordError {
+    #[error("Recording IO error")]
+    Io(#[from] io::Error),
+    #[error("Malformed record at line {line}")]
+    Malformed { line: usize },
+    #[error("Invalid hex data at line {line}")]
+    Hex { line: usize, #[source] error: hex::FromHexError },
+    #[error("Failed to encode frame")]
+    Frame(#[from] proto::FrameError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// 距录制开始的时间
+    pub at: Duration,
+    pub bytes: Vec<u8>,
+}
+
+/// [`Recorder`] 刷新输出的时机
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// 距上次刷新超过该间隔后，在写入下一条记录时刷新
+    Interval(Duration),
+    /// 每条记录后立即刷新，进程意外退出时不丢失数据，但每次读取都会多一次系统调用
+    EveryRecord,
+}
+
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        FlushPolicy::Interval(Duration::from_secs(1))
+    }
+}
+
+/// 录制器
+///
+/// 记录按 [`FlushPolicy`] 刷新，结束时应调用 [`Recorder::finish`]；
+/// [`Recorder::create`] 使用的 `BufWriter` 在丢弃时也会写出剩余的记录。
+pub struct Recorder<W: Write> {
+    out: W,
+    start: Instant,
+    flush_policy: FlushPolicy,
+    last_flush: Instant,
+}
+
+impl Recorder<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// 以创建时刻作为录制起点
+    pub fn new(out: W) -> Self {
+        let start = Instant::now();
+        Self { out, start, flush_policy: FlushPolicy::default(), last_flush: start }
+    }
+
+    pub fn flush_policy(mut self, flush_policy: FlushPolicy) -> Self {
+        self.flush_policy = flush_policy;
+        self
+    }
+
+    pub fn record(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if bytes.is_empty() { return Ok(()); }
+        writeln!(self.out, "{} {}", self.start.elapsed().as_micros(), hex::encode(bytes))?;
+        let due = match self.flush_policy {
+            FlushPolicy::Interval(interval) => self.last_flush.elapsed() >= interval,
+            FlushPolicy::EveryRecord => true,
+        };
+        if due { self.flush()?; }
+        Ok(())
+    }
+
+    /// 按线上格式重新编码后记录一帧
+    pub fn record_frame(&mut self, frame: &proto::Frame2) -> Result<(), RecordError> {
+        self.record(&frame.to_wire_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.out.flush()
+    }
+
+    /// 刷新后返回输出
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.out)
+    }
+
+    /// 不刷新，直接返回输出
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> std::fmt::Debug for Recorder<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recorder").field("start", &self.start).finish_non_exhaustive()
+    }
+}
+
+/// 逐行读取录制文件
+pub struct RecordReader<R: BufRead> {
+    lines: Lines<R>,
+    line: usize,
+}
+
+impl RecordReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> RecordReader<R> {
+    pub fn new(input: R) -> Self {
+        Self { lines: input.lines(), line: 0 }
+    }
+}
+
+impl<R: BufRead> Iterator for RecordReader<R> {
+    type Item = Result<Record, RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            self.line += 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            return Some(parse_line(line, self.line));
+        }
+    }
+}
+
+fn parse_line(line: &str, number: usize) -> Result<Record, RecordError> {
+    let (micros, data) = line.split_once(' ').ok_or(RecordError::Malformed { line: number })?;
+    let micros: u64 = micros.parse().map_err(|_| RecordError::Malformed { line: number })?;
+    let bytes = hex::decode(data.trim()).map_err(|error| RecordError::Hex { line: number, error })?;
+    Ok(Record { at: Duration::from_micros(micros), bytes })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// 按录制时的间隔回放
+    RealTime,
+    /// 按倍率回放，大于 1 为加速，非正数或非有限值视为 [`ReplaySpeed::Stepped`]
+    Scaled(f64),
+    /// 不等待，由调用方逐条取出
+    Stepped,
+}
+
+/// 按指定速度回放录制文件
+///
+/// 作为迭代器使用时返回每条记录及其应当发出的时刻，第一条记录的时刻为首次取出时；
+/// [`Replayer::replay`] 与 [`Replayer::replay_async`] 会等到该时刻再把数据写入目标。
+pub struct Replayer<R: BufRead> {
+    records: RecordReader<R>,
+    speed: ReplaySpeed,
+    origin: Option<(Instant, Duration)>,
+}
+
+impl Replayer<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>, speed: ReplaySpeed) -> io::Result<Self> {
+        Ok(Self::new(RecordReader::open(path)?, speed))
+    }
+}
+
+impl<R: BufRead> Replayer<R> {
+    pub fn new(records: RecordReader<R>, speed: ReplaySpeed) -> Self {
+        Self { records, speed, origin: None }
+    }
+
+    fn deadline(&mut self, at: Duration) -> Instant {
+        let factor = match self.speed {
+            ReplaySpeed::RealTime => 1.0,
+            ReplaySpeed::Scaled(factor) if factor.is_finite() && factor > 0.0 => factor,
+            ReplaySpeed::Scaled(_) | ReplaySpeed::Stepped => return Instant::now(),
+        };
+        let (start, first) = *self.origin.get_or_insert_with(|| (Instant::now(), at));
+        start + at.saturating_sub(first).div_f64(factor)
+    }
+
+    /// 回放到阻塞式的写入目标，例如串口或 TCP 连接
+    pub fn replay(self, out: &mut impl Write) -> Result<(), RecordError> {
+        for item in self {
+            let (record, deadline) = item?;
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+            out.write_all(&record.bytes)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio_client")]
+    pub async fn replay_async(self, out: &mut (impl tokio::io::AsyncWrite + Unpin)) -> Result<(), RecordError> {
+        use tokio::io::AsyncWriteExt;
+
+        for item in self {
+            let (record, deadline) = item?;
+            tokio::time::sleep_until(deadline.into()).await;
+            out.write_all(&record.bytes).await?;
+        }
+        out.flush().await?;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for Replayer<R> {
+    type Item = Result<(Record, Instant), RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.records.next()?.map(|record| {
+            let deadline = self.deadline(record.at);
+            (record, deadline)
+        }))
+    }
+}
+
+#[cfg(feature = "tokio_client")]
+pub use self::tokio_io::RecordingIo;
+
+#[cfg(feature = "tokio_client")]
+mod tokio_io {
+    use std::io::{self, Write};
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+    use tracing::warn;
+
+    use super::Recorder;
+
+    /// 包装任意字节流，把读到的原始数据写入 [`Recorder`]，写入方向原样透传
+    ///
+    /// 用于 [`crate::tokio_client::connect_io`]，录制失败只记录日志，不影响连接本身。
+    /// 读到流末尾时刷新录制器，其余时候按其 [`super::FlushPolicy`] 刷新。
+    pub struct RecordingIo<T, W: Write> {
+        inner: T,
+        recorder: Recorder<W>,
+    }
+
+    impl<T, W: Write> RecordingIo<T, W> {
+        pub fn new(inner: T, recorder: Recorder<W>) -> Self {
+            Self { inner, recorder }
+        }
+
+        pub fn into_inner(self) -> (T, Recorder<W>) {
+            (self.inner, self.recorder)
+        }
+    }
+
+    impl<T: AsyncRead + Unpin, W: Write + Unpin> AsyncRead for RecordingIo<T, W> {
+        fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+            let filled = buf.filled().len();
+            let eof_possible = buf.remaining() > 0;
+            let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+            if let Poll::Ready(Ok(())) = poll {
+                let read = &buf.filled()[filled..];
+                let result = if read.is_empty() && eof_possible {
+                    this.recorder.flush()
+                } else {
+                    this.recorder.record(read)
+                };
+                if let Err(err) = result {
+                    warn!("Failed to record: {}", err);
+                }
+            }
+            poll
+        }
+    }
+
+    impl<T: AsyncWrite + Unpin, W: Write + Unpin> AsyncWrite for RecordingIo<T, W> {
+        fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+        }
+    }
+}
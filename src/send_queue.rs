@@ -0,0 +1,225 @@
+//! 按裁判系统上行带宽限制发送的优先级队列，与 IO 无关
+//!
+//! 超出带宽的数据会被裁判系统直接丢弃，表现为 UI 闪烁或小地图标记丢失。
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use deku::DekuError;
+
+use crate::proto::{Message, FRAME_OVERHEAD, MAX_DATA_LENGTH};
+
+#[derive(thiserror::Error, Debug)]
+pub enum SendQueueError {
+    #[error("Send queue is full")]
+    Full,
+    #[error("Failed to encode message")]
+    Deku(#[from] DekuError),
+    #[error("Message too large: {0} bytes")]
+    TooLarge(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// 例如 UI 重绘
+    Low,
+    #[default]
+    Normal,
+    /// 例如机器人之间的指令
+    High,
+}
+
+#[derive(Debug, Clone)]
+pub struct SendQueueConfig {
+    /// 全部上行数据的字节预算，按整帧长度计算
+    pub bytes_per_second: u32,
+    /// 字节预算允许的突发量，不足一帧最大长度时按一帧计算
+    pub burst_bytes: u32,
+    /// 按 cmd_id 的发送频率上限，单位 Hz，非正数视为不限制
+    pub cmd_rate_limits: HashMap<u16, f64>,
+    /// 最多排队的消息数
+    pub capacity: usize,
+}
+
+impl Default for SendQueueConfig {
+    fn default() -> Self {
+        Self {
+            bytes_per_second: 3720,
+            burst_bytes: 372,
+            cmd_rate_limits: HashMap::from([(0x0301, 30.0), (0x0305, 10.0)]),
+            capacity: 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, capacity: f64, now: Instant) -> Self {
+        Self { rate, capacity, tokens: capacity, last: now }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last = now;
+    }
+
+    fn available(&self, amount: f64, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        (self.tokens + elapsed * self.rate).min(self.capacity) >= amount
+    }
+
+    fn take(&mut self, amount: f64, now: Instant) {
+        self.refill(now);
+        self.tokens -= amount;
+    }
+
+    fn ready_at(&self, amount: f64) -> Instant {
+        let missing = (amount - self.tokens).max(0.0);
+        self.last + Duration::from_secs_f64(missing / self.rate)
+    }
+}
+
+#[derive(Debug)]
+struct Queued {
+    message: Message,
+    priority: Priority,
+    coalesce_key: Option<u64>,
+    size: u16,
+}
+
+/// 上行发送队列
+///
+/// 按优先级从高到低、同优先级先进先出的顺序取出未超出 cmd_id 频率上限的消息，总字节预算不足时即使低优先级消息更小也不会插队。
+/// 带有相同 cmd_id 与合并键的消息会替换仍在排队的旧消息。队列满时丢弃优先级最低的最早消息。
+#[derive(Debug)]
+pub struct SendQueue {
+    config: SendQueueConfig,
+    total: TokenBucket,
+    per_cmd: HashMap<u16, TokenBucket>,
+    queue: VecDeque<Queued>,
+    dropped: u64,
+}
+
+impl SendQueue {
+    pub fn new(config: SendQueueConfig) -> Self {
+        Self::new_at(config, Instant::now())
+    }
+
+    pub fn new_at(config: SendQueueConfig, now: Instant) -> Self {
+        let burst = config.burst_bytes.max((MAX_DATA_LENGTH as usize + FRAME_OVERHEAD) as u32);
+        let total = TokenBucket::new(config.bytes_per_second.max(1) as f64, burst as f64, now);
+        let per_cmd = config.cmd_rate_limits.iter()
+            .filter(|(_, &hz)| hz > 0.0)
+            .map(|(&cmd_id, &hz)| (cmd_id, TokenBucket::new(hz, 1.0, now)))
+            .collect();
+        Self { config, total, per_cmd, queue: VecDeque::new(), dropped: 0 }
+    }
+
+    pub fn push(&mut self, message: Message, priority: Priority) -> Result<(), SendQueueError> {
+        self.enqueue(message, priority, None)
+    }
+
+    /// 替换仍在排队的同 cmd_id、同 `key` 的消息，替换后的消息保留原有位置并取两者中较高的优先级
+    pub fn push_coalesced(&mut self, message: Message, priority: Priority, key: u64) -> Result<(), SendQueueError> {
+        self.enqueue(message, priority, Some(key))
+    }
+
+    fn enqueue(&mut self, message: Message, priority: Priority, coalesce_key: Option<u64>) -> Result<(), SendQueueError> {
+        let data_length = message.data_length()?;
+        if data_length > MAX_DATA_LENGTH {
+            return Err(SendQueueError::TooLarge(data_length));
+        }
+        let size = data_length + FRAME_OVERHEAD as u16;
+
+        if let Some(key) = coalesce_key {
+            let cmd_id = message.cmd_id();
+            let superseded = self.queue.iter_mut()
+                .find(|queued| queued.coalesce_key == Some(key) && queued.message.cmd_id() == cmd_id);
+            if let Some(queued) = superseded {
+                queued.message = message;
+                queued.priority = queued.priority.max(priority);
+                queued.size = size;
+                return Ok(());
+            }
+        }
+
+        if self.queue.len() >= self.config.capacity {
+            let lowest = self.queue.iter().enumerate()
+                .min_by_key(|(index, queued)| (queued.priority, *index))
+                .map(|(index, queued)| (index, queued.priority));
+            match lowest {
+                Some((index, lowest)) if lowest <= priority => {
+                    self.queue.remove(index);
+                    self.dropped += 1;
+                }
+                _ => {
+                    self.dropped += 1;
+                    return Err(SendQueueError::Full);
+                }
+            }
+        }
+        self.queue.push_back(Queued { message, priority, coalesce_key, size });
+        Ok(())
+    }
+
+    fn next_index(&self, now: Instant) -> Option<usize> {
+        let mut candidates: Vec<usize> = (0..self.queue.len()).collect();
+        candidates.sort_by_key(|&index| std::cmp::Reverse(self.queue[index].priority));
+        candidates.into_iter().find(|&index| {
+            let cmd_id = self.queue[index].message.cmd_id();
+            self.per_cmd.get(&cmd_id).is_none_or(|bucket| bucket.available(1.0, now))
+        })
+    }
+
+    /// 取出下一条可以立即发送的消息并扣除预算
+    pub fn pop(&mut self, now: Instant) -> Option<Message> {
+        let index = self.next_index(now)?;
+        let size = self.queue[index].size as f64;
+        if !self.total.available(size, now) { return None; }
+        let queued = self.queue.remove(index)?;
+        self.total.take(size, now);
+        if let Some(bucket) = self.per_cmd.get_mut(&queued.message.cmd_id()) {
+            bucket.take(1.0, now);
+        }
+        Some(queued.message)
+    }
+
+    /// 队列非空时下一次可能有消息可发的时刻
+    pub fn next_ready(&self, now: Instant) -> Option<Instant> {
+        if self.queue.is_empty() { return None; }
+        if let Some(index) = self.next_index(now) {
+            let mut total = self.total.clone();
+            total.refill(now);
+            return Some(total.ready_at(self.queue[index].size as f64).max(now));
+        }
+        self.queue.iter()
+            .filter_map(|queued| self.per_cmd.get(&queued.message.cmd_id()))
+            .map(|bucket| {
+                let mut bucket = bucket.clone();
+                bucket.refill(now);
+                bucket.ready_at(1.0)
+            })
+            .min()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// 因队列已满被丢弃的消息数
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
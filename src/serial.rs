@@ -0,0 +1,209 @@
+//! 两种客户端共用的串口参数、串口查找与断线重连参数
+
+use std::fmt;
+use std::time::Duration;
+
+pub use serialport::{DataBits, FlowControl, Parity, SerialPortInfo, SerialPortType, StopBits};
+
+use crate::proto::ProtocolVersion;
+
+/// 串口参数与协议版本，默认与裁判系统一致：115200 8N1，无流控
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use rmreco::proto::ProtocolVersion;
+/// # use rmreco::serial::SerialConfig;
+/// let config = SerialConfig::new().baud_rate(921600).timeout(Duration::from_millis(200)).version(ProtocolVersion::V2024);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SerialConfig {
+    pub baud_rate: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+    /// 阻塞式客户端单次读取的超时，超时后检查是否需要停止
+    pub timeout: Duration,
+    /// 阻塞式客户端单次读取的缓冲区大小
+    pub read_buffer_size: usize,
+    /// 解析与编码使用的协议版本
+    pub version: ProtocolVersion,
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self {
+            baud_rate: 115200,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+            timeout: Duration::from_millis(1000),
+            read_buffer_size: 256,
+            version: ProtocolVersion::default(),
+        }
+    }
+}
+
+impl SerialConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    pub fn data_bits(mut self, data_bits: DataBits) -> Self {
+        self.data_bits = data_bits;
+        self
+    }
+
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    pub fn stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    pub fn flow_control(mut self, flow_control: FlowControl) -> Self {
+        self.flow_control = flow_control;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.read_buffer_size = read_buffer_size.max(1);
+        self
+    }
+
+    pub fn version(mut self, version: ProtocolVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// 按当前参数打开 `path` 的 [`serialport::SerialPortBuilder`]
+    pub fn builder(&self, path: &str) -> serialport::SerialPortBuilder {
+        serialport::new(path, self.baud_rate)
+            .data_bits(self.data_bits)
+            .parity(self.parity)
+            .stop_bits(self.stop_bits)
+            .flow_control(self.flow_control)
+            .timeout(self.timeout)
+    }
+}
+
+/// 列出系统中的全部串口
+pub fn list_ports() -> serialport::Result<Vec<SerialPortInfo>> {
+    serialport::available_ports()
+}
+
+/// 列出 VID 及可选的 PID 匹配的 USB 串口
+pub fn usb_ports(vid: u16, pid: Option<u16>) -> serialport::Result<Vec<SerialPortInfo>> {
+    Ok(list_ports()?.into_iter()
+        .filter(|info| match &info.port_type {
+            SerialPortType::UsbPort(usb) => usb.vid == vid && pid.is_none_or(|pid| usb.pid == pid),
+            _ => false,
+        })
+        .collect())
+}
+
+/// 可能连接着裁判系统的串口，即全部 USB 串口
+pub fn candidate_ports() -> serialport::Result<Vec<SerialPortInfo>> {
+    Ok(list_ports()?.into_iter()
+        .filter(|info| matches!(info.port_type, SerialPortType::UsbPort(_)))
+        .collect())
+}
+
+/// 要打开的串口
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortSelector {
+    Path(String),
+    /// 按 USB VID/PID 及可选的序列号查找，适用于重新插拔后设备路径发生变化的情况
+    Usb { vid: u16, pid: u16, serial_number: Option<String> },
+}
+
+impl PortSelector {
+    pub fn matches(&self, info: &SerialPortInfo) -> bool {
+        match (self, &info.port_type) {
+            (PortSelector::Path(path), _) => &info.port_name == path,
+            (PortSelector::Usb { vid, pid, serial_number }, SerialPortType::UsbPort(usb)) => {
+                usb.vid == *vid && usb.pid == *pid
+                    && serial_number.as_ref().is_none_or(|serial_number| usb.serial_number.as_ref() == Some(serial_number))
+            }
+            (PortSelector::Usb { .. }, _) => false,
+        }
+    }
+
+    /// 当前对应的设备路径
+    pub fn resolve(&self) -> Result<String, serialport::Error> {
+        match self {
+            PortSelector::Path(path) => Ok(path.clone()),
+            PortSelector::Usb { .. } => list_ports()?.into_iter()
+                .find(|info| self.matches(info))
+                .map(|info| info.port_name)
+                .ok_or_else(|| serialport::Error::new(serialport::ErrorKind::NoDevice, format!("No serial port matches {}", self))),
+        }
+    }
+}
+
+impl fmt::Display for PortSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortSelector::Path(path) => f.write_str(path),
+            PortSelector::Usb { vid, pid, serial_number: None } => write!(f, "USB {:04x}:{:04x}", vid, pid),
+            PortSelector::Usb { vid, pid, serial_number: Some(serial_number) } => write!(f, "USB {:04x}:{:04x} ({})", vid, pid, serial_number),
+        }
+    }
+}
+
+impl From<&str> for PortSelector {
+    fn from(path: &str) -> Self {
+        PortSelector::Path(path.to_owned())
+    }
+}
+
+impl From<String> for PortSelector {
+    fn from(path: String) -> Self {
+        PortSelector::Path(path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    /// 已连接到该设备路径
+    Connected(String),
+    /// 断开或打开失败的原因，之后会自动重试
+    Disconnected(String),
+    /// 不再重连
+    Closed,
+}
+
+/// 重连间隔，第 n 次失败后等待 `initial * factor^n`，不超过 `max`
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub factor: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self { initial: Duration::from_millis(100), max: Duration::from_secs(5), factor: 2 }
+    }
+}
+
+impl Backoff {
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial.saturating_mul(self.factor.saturating_pow(attempt)).min(self.max)
+    }
+}
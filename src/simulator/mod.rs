@@ -0,0 +1,497 @@
+//! 模拟裁判系统，在任意传输上按协议规定的频率发送比赛数据，并校验机器人发来的 0x0301 与 0x0305 数据
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::select;
+use tokio::sync::mpsc;
+use tokio::time::{sleep_until, Instant};
+use tracing::{debug, info, warn};
+
+use crate::proto;
+use crate::proto::id::{RobotJob, Side};
+use crate::proto::{v2023, v2024, GameProgress, Message, ProtocolVersion};
+use crate::tokio_client;
+use crate::tokio_client::codec::RefereeCodecError;
+
+mod scenario;
+
+pub use scenario::{Scenario, ScenarioError, ScenarioEvent};
+
+/// 比赛各阶段的持续时间，依次为赛前、准备、自检、五秒倒计时、比赛中、结算
+#[derive(Debug, Clone)]
+pub struct MatchTimeline {
+    pub pre_competition: Duration,
+    pub setup: Duration,
+    pub initialization: Duration,
+    pub countdown: Duration,
+    pub combat: Duration,
+    pub calculating: Duration,
+    pub winner: proto::Winner,
+}
+
+impl Default for MatchTimeline {
+    fn default() -> Self {
+        Self {
+            pre_competition: Duration::from_secs(5),
+            setup: Duration::from_secs(10),
+            initialization: Duration::from_secs(15),
+            countdown: Duration::from_secs(5),
+            combat: Duration::from_secs(420),
+            calculating: Duration::from_secs(5),
+            winner: proto::Winner::Draw,
+        }
+    }
+}
+
+impl MatchTimeline {
+    fn stages(&self) -> [(GameProgress, Duration); 6] {
+        [
+            (GameProgress::PreCompetitionStage, self.pre_competition),
+            (GameProgress::SetupPeriod, self.setup),
+            (GameProgress::InitializationStage, self.initialization),
+            (GameProgress::FiveSecondCountdown, self.countdown),
+            (GameProgress::InCombat, self.combat),
+            (GameProgress::CalculatingCompetitionResults, self.calculating),
+        ]
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages().iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    pub version: ProtocolVersion,
+    pub game_type: proto::GameType,
+    /// 被测机器人的 ID，决定发送哪一方的数据以及上行数据的校验规则
+    pub robot_id: u8,
+    pub timeline: MatchTimeline,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            version: ProtocolVersion::default(),
+            game_type: proto::GameType::RMUC,
+            robot_id: 3,
+            timeline: MatchTimeline::default(),
+        }
+    }
+}
+
+/// 对机器人上行数据的校验结果
+#[derive(Debug)]
+pub enum UplinkReport {
+    Accepted(proto::Frame2),
+    Rejected { frame: Option<proto::Frame2>, reason: UplinkError },
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum UplinkError {
+    #[error("Robots may not send cmd_id {0:#06x}")]
+    UnexpectedCmd(u16),
+    #[error("send_id {send_id} does not match robot id {robot_id}")]
+    SenderMismatch { send_id: u16, robot_id: u8 },
+    #[error("Header content_id {header:#06x} does not match payload content_id {payload:#06x}")]
+    ContentIdMismatch { header: u16, payload: u16 },
+    #[error("content_id {0:#06x} is not allowed")]
+    InvalidContentId(u16),
+    #[error("receive_id {0:#06x} is not a valid receiver")]
+    InvalidReceiver(u16),
+    #[error("Only radar may send minimap receipts")]
+    NotRadar,
+    #[error("Target {0} is not an enemy robot")]
+    InvalidTarget(u16),
+    #[error("Position ({0}, {1}) is outside of the field")]
+    OutOfField(f32, f32),
+    #[error("Corrupted uplink data")]
+    Corrupted(#[from] RefereeCodecError),
+}
+
+/// 周期发送的消息，频率参照 2023 赛季协议
+#[derive(Debug, Clone, Copy)]
+enum Periodic {
+    GameStatus,
+    GameRobotHP,
+    EventData,
+    GameRobotStatus,
+    PowerHeatData,
+    GameRobotPos,
+    PowerRuneBuff,
+    BulletRemaining,
+    RFIDStatus,
+    GroundRobotPosition,
+    RadarMarkData,
+}
+
+impl Periodic {
+    const ALL: [Periodic; 11] = [
+        Periodic::GameStatus,
+        Periodic::GameRobotHP,
+        Periodic::EventData,
+        Periodic::GameRobotStatus,
+        Periodic::PowerHeatData,
+        Periodic::GameRobotPos,
+        Periodic::PowerRuneBuff,
+        Periodic::BulletRemaining,
+        Periodic::RFIDStatus,
+        Periodic::GroundRobotPosition,
+        Periodic::RadarMarkData,
+    ];
+
+    fn period(self) -> Duration {
+        let hz = match self {
+            Periodic::GameStatus => 1,
+            Periodic::GameRobotHP => 1,
+            Periodic::EventData => 1,
+            Periodic::GameRobotStatus => 10,
+            Periodic::PowerHeatData => 50,
+            Periodic::GameRobotPos => 10,
+            Periodic::PowerRuneBuff => 1,
+            Periodic::BulletRemaining => 10,
+            Periodic::RFIDStatus => 3,
+            Periodic::GroundRobotPosition => 1,
+            Periodic::RadarMarkData => 1,
+        };
+        Duration::from_secs(1) / hz
+    }
+
+    fn cmd_id(self) -> u16 {
+        match self {
+            Periodic::GameStatus => 0x0001,
+            Periodic::GameRobotHP => 0x0003,
+            Periodic::EventData => 0x0101,
+            Periodic::GameRobotStatus => 0x0201,
+            Periodic::PowerHeatData => 0x0202,
+            Periodic::GameRobotPos => 0x0203,
+            Periodic::PowerRuneBuff => 0x0204,
+            Periodic::BulletRemaining => 0x0208,
+            Periodic::RFIDStatus => 0x0209,
+            Periodic::GroundRobotPosition => 0x020B,
+            Periodic::RadarMarkData => 0x020C,
+        }
+    }
+}
+
+fn initial_hp(job: RobotJob) -> u16 {
+    match job {
+        RobotJob::Hero => 200,
+        RobotJob::Engineer => 500,
+        RobotJob::Infantry3 | RobotJob::Infantry4 | RobotJob::Infantry5 => 200,
+        RobotJob::Sentry => 600,
+        RobotJob::Drone | RobotJob::Dart | RobotJob::Radar => 0,
+    }
+}
+
+fn initial_team_hp() -> proto::TeamHP {
+    let mut hp = proto::TeamHP { outpost: 1500, base: 5000, ..Default::default() };
+    for job in RobotJob::LAND_ROBOT {
+        if let Some(slot) = hp.robot_hp_mut(job) {
+            *slot = initial_hp(job);
+        }
+    }
+    hp
+}
+
+/// 模拟裁判系统
+///
+/// 通过 [`RefereeSimulator::run`] 在给定传输上跑完一整场比赛，机器人上行数据的校验结果从 [`RefereeSimulator::new`]
+/// 返回的接收端取出，不需要时可直接丢弃。
+///
+/// 通过 [`RefereeSimulator::with_scenario`] 加载的脚本事件在到时后发送，其中与周期数据同一 cmd_id 的消息会替换之后的周期数据，
+/// 脚本发出 [`Message::GameResult`] 时比赛提前结束，时间线结束后仍未发送的事件被丢弃。
+pub struct RefereeSimulator {
+    config: SimulatorConfig,
+    reports: mpsc::UnboundedSender<UplinkReport>,
+    red: proto::TeamHP,
+    blue: proto::TeamHP,
+    scenario: VecDeque<ScenarioEvent>,
+    overrides: HashMap<u16, Message>,
+}
+
+impl RefereeSimulator {
+    pub fn new(config: SimulatorConfig) -> (Self, mpsc::UnboundedReceiver<UplinkReport>) {
+        let (reports, receiver) = mpsc::unbounded_channel();
+        let simulator = Self {
+            config,
+            reports,
+            red: initial_team_hp(),
+            blue: initial_team_hp(),
+            scenario: VecDeque::new(),
+            overrides: HashMap::new(),
+        };
+        (simulator, receiver)
+    }
+
+    /// 脚本中的消息与 [`SimulatorConfig::version`] 不符时返回错误
+    pub fn with_scenario(mut self, scenario: Scenario) -> Result<Self, ScenarioError> {
+        scenario.validate(self.config.version)?;
+        let mut events = scenario.events;
+        events.sort_by_key(|event| event.at);
+        self.scenario = events.into();
+        Ok(self)
+    }
+
+    pub async fn run<T>(mut self, io: T) -> Result<(), RefereeCodecError>
+        where T: AsyncRead + AsyncWrite + Send + 'static {
+        let (mut reader, mut writer) = tokio_client::connect_io(io, self.config.version);
+        let start = Instant::now();
+        let mut periodic: Vec<(Periodic, Instant)> = Periodic::ALL.iter().map(|&kind| (kind, start)).collect();
+        let mut stages = self.config.timeline.stages().into_iter();
+        let (mut stage, duration) = stages.next().unwrap();
+        let mut stage_end = start + duration;
+        info!("Entering {:?}", stage);
+
+        loop {
+            let now = Instant::now();
+            while now >= stage_end {
+                match stages.next() {
+                    Some((next, duration)) => {
+                        stage = next;
+                        stage_end += duration;
+                        info!("Entering {:?}", stage);
+                    }
+                    None => {
+                        writer.send_message(Message::GameResult(self.config.timeline.winner.clone())).await?;
+                        info!("Match finished, winner: {:?}", self.config.timeline.winner);
+                        return Ok(());
+                    }
+                }
+            }
+
+            while self.scenario.front().is_some_and(|event| start + event.at <= now) {
+                let message = self.scenario.pop_front().unwrap().message;
+                info!("Scenario event {:?}", message);
+                let finished = matches!(message, Message::GameResult(_));
+                self.apply(&message);
+                writer.send_message(message).await?;
+                if finished {
+                    info!("Match finished by scenario");
+                    return Ok(());
+                }
+            }
+
+            let remain = stage_end.saturating_duration_since(now);
+            for (kind, due) in periodic.iter_mut() {
+                if *due > now { continue; }
+                if let Some(message) = self.periodic_message(*kind, &stage, remain) {
+                    writer.send_message(message).await?;
+                }
+                while *due <= now {
+                    *due += kind.period();
+                }
+            }
+
+            let mut next_due = periodic.iter().map(|(_, due)| *due).min().unwrap().min(stage_end);
+            if let Some(event) = self.scenario.front() {
+                next_due = next_due.min(start + event.at);
+            }
+            select! {
+                _ = sleep_until(next_due) => {}
+                received = reader.recv() => match received {
+                    Some(received) => self.check_uplink(received),
+                    None => {
+                        info!("Robot side closed the connection");
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    fn side(&self) -> Side {
+        Side::from_id(self.config.robot_id).unwrap_or(Side::Red)
+    }
+
+    fn job(&self) -> Option<RobotJob> {
+        RobotJob::from_id_with_side(self.config.robot_id).map(|(_, job)| job)
+    }
+
+    fn own_team_hp(&self) -> &proto::TeamHP {
+        match self.side() {
+            Side::Red => &self.red,
+            Side::Blue => &self.blue,
+        }
+    }
+
+    /// 脚本消息对之后周期数据的影响，比赛阶段始终由时间线决定，`RobotHurt` 不影响血量
+    fn apply(&mut self, message: &Message) {
+        match message {
+            Message::GameRobotHP { red, blue } => {
+                self.red = red.clone();
+                self.blue = blue.clone();
+            }
+            Message::GameStatus(_) => {}
+            other => {
+                let cmd_id = other.cmd_id();
+                if Periodic::ALL.iter().any(|kind| kind.cmd_id() == cmd_id) {
+                    self.overrides.insert(cmd_id, other.clone());
+                }
+            }
+        }
+    }
+
+    fn periodic_message(&self, kind: Periodic, stage: &GameProgress, remain: Duration) -> Option<Message> {
+        if let Some(message) = self.overrides.get(&kind.cmd_id()) {
+            return Some(message.clone());
+        }
+        let version = self.config.version;
+        let message = match kind {
+            Periodic::GameStatus => Message::GameStatus(proto::GameStatus {
+                game_type: self.config.game_type.clone(),
+                game_progress: stage.clone(),
+                stage_remain_time: remain.as_secs() as u16,
+                sync_timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs()),
+            }),
+            Periodic::GameRobotHP => Message::GameRobotHP { red: self.red.clone(), blue: self.blue.clone() },
+            Periodic::EventData => Message::EventData(match version {
+                ProtocolVersion::V2023 => proto::EventData::V2023(v2023::EventData {
+                    base_has_virtual_shield: true,
+                    outpost_survives: true,
+                    ..Default::default()
+                }),
+                ProtocolVersion::V2024 | ProtocolVersion::V2025 => proto::EventData::V2024(v2024::EventData {
+                    base_virtual_shield_percentage: 100,
+                    ..Default::default()
+                }),
+            }),
+            Periodic::GameRobotStatus => Message::GameRobotStatus(self.game_robot_status()),
+            Periodic::PowerHeatData => Message::PowerHeatData(proto::PowerHeatData {
+                chassis_volt: 24000,
+                chassis_current: 2000,
+                chassis_power: 48.0,
+                chassis_power_buffer: 60,
+                shooter_id1_17mm_cooling_heat: 0,
+                shooter_id2_17mm_cooling_heat: 0,
+                shooter_id1_42mm_cooling_heat: 0,
+            }),
+            Periodic::GameRobotPos => {
+                let (x, y, yaw) = match self.side() {
+                    Side::Red => (2.0, 2.0, 0.0),
+                    Side::Blue => (26.0, 13.0, 180.0),
+                };
+                Message::GameRobotPos(proto::GameRobotPos { x, y, z: 0.0, yaw })
+            }
+            Periodic::PowerRuneBuff => Message::PowerRuneBuff(Default::default()),
+            Periodic::BulletRemaining => Message::BulletRemaining(proto::BulletRemaining {
+                bullet_remaining_num_17mm: 400,
+                bullet_remaining_num_42mm: 0,
+                coin_remaining_num: 400,
+            }),
+            Periodic::RFIDStatus => Message::RFIDStatus(match version {
+                ProtocolVersion::V2023 => proto::RFIDStatus::V2023(Default::default()),
+                ProtocolVersion::V2024 | ProtocolVersion::V2025 => proto::RFIDStatus::V2024(Default::default()),
+            }),
+            Periodic::GroundRobotPosition if self.job() == Some(RobotJob::Sentry) => Message::GroundRobotPosition(Default::default()),
+            Periodic::RadarMarkData if self.job() == Some(RobotJob::Radar) => Message::RadarMarkData(Default::default()),
+            Periodic::GroundRobotPosition | Periodic::RadarMarkData => return None,
+        };
+        Some(message)
+    }
+
+    fn game_robot_status(&self) -> proto::GameRobotStatus {
+        let robot_id = self.config.robot_id;
+        let max_hp = self.job().map_or(0, initial_hp);
+        let remain_hp = self.job().and_then(|job| self.own_team_hp().robot_hp(job)).unwrap_or(0);
+        match self.config.version {
+            ProtocolVersion::V2023 => proto::GameRobotStatus::V2023(v2023::GameRobotStatus {
+                robot_id,
+                robot_level: 1,
+                remain_hp,
+                max_hp,
+                shooter_id1_17mm_cooling_rate: 40,
+                shooter_id1_17mm_cooling_limit: 150,
+                shooter_id1_17mm_speed_limit: 15,
+                chassis_power_limit: 60,
+                mains_power_gimbal_output: true,
+                mains_power_chassis_output: true,
+                mains_power_shooter_output: true,
+                ..Default::default()
+            }),
+            ProtocolVersion::V2024 | ProtocolVersion::V2025 => proto::GameRobotStatus::V2024(v2024::GameRobotStatus {
+                robot_id,
+                robot_level: 1,
+                remain_hp,
+                max_hp,
+                shooter_barrel_cooling_value: 10,
+                shooter_barrel_heat_limit: 50,
+                chassis_power_limit: 45,
+                mains_power_gimbal_output: true,
+                mains_power_chassis_output: true,
+                mains_power_shooter_output: true,
+            }),
+        }
+    }
+
+    fn check_uplink(&self, received: Result<proto::Frame2, RefereeCodecError>) {
+        let report = match received {
+            Ok(frame) => match self.validate(&frame.message) {
+                Ok(()) => {
+                    debug!("Accepted uplink {:?}", frame.message);
+                    UplinkReport::Accepted(frame)
+                }
+                Err(reason) => {
+                    warn!("Rejected uplink {:?}: {}", frame.message, reason);
+                    UplinkReport::Rejected { frame: Some(frame), reason }
+                }
+            },
+            Err(err) => {
+                warn!("Corrupted uplink: {}", err);
+                UplinkReport::Rejected { frame: None, reason: err.into() }
+            }
+        };
+        // 调用方可以不关心校验结果
+        let _ = self.reports.send(report);
+    }
+
+    fn validate(&self, message: &Message) -> Result<(), UplinkError> {
+        let robot_id = self.config.robot_id;
+        match message {
+            Message::StudentInteractiveData(data) => {
+                if data.send_id != robot_id as u16 {
+                    return Err(UplinkError::SenderMismatch { send_id: data.send_id, robot_id });
+                }
+                let payload = data.get_content_id();
+                if payload != data.content_id {
+                    return Err(UplinkError::ContentIdMismatch { header: data.content_id, payload });
+                }
+                match data.content_id {
+                    0x0200..=0x02FF => {
+                        let same_side = u8::try_from(data.receive_id).ok()
+                            .and_then(RobotJob::from_id_with_side)
+                            .is_some_and(|(side, _)| side == self.side());
+                        if !same_side || data.receive_id == robot_id as u16 {
+                            return Err(UplinkError::InvalidReceiver(data.receive_id));
+                        }
+                    }
+                    0x0100..=0x0104 | 0x0110 => {
+                        if data.receive_id != 0x0100 + robot_id as u16 {
+                            return Err(UplinkError::InvalidReceiver(data.receive_id));
+                        }
+                    }
+                    other => return Err(UplinkError::InvalidContentId(other)),
+                }
+                Ok(())
+            }
+            Message::MinimapReceipt { target_robot_id, target_position: (x, y) } => {
+                if self.job() != Some(RobotJob::Radar) {
+                    return Err(UplinkError::NotRadar);
+                }
+                let enemy = u8::try_from(*target_robot_id).ok()
+                    .and_then(RobotJob::from_id_with_side)
+                    .is_some_and(|(side, _)| side == self.side().opposite());
+                if !enemy {
+                    return Err(UplinkError::InvalidTarget(*target_robot_id));
+                }
+                if !(0.0..=28.0).contains(x) || !(0.0..=15.0).contains(y) {
+                    return Err(UplinkError::OutOfField(*x, *y));
+                }
+                Ok(())
+            }
+            other => Err(UplinkError::UnexpectedCmd(other.cmd_id())),
+        }
+    }
+}
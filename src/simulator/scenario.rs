@@ -0,0 +1,172 @@
+//! 模拟裁判系统的比赛脚本，按时间列出在周期数据之外额外发送的消息
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::proto::{Message, ProtocolVersion};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScenarioError {
+    #[error("Failed to read scenario file")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid TOML scenario")]
+    Toml(#[from] toml::de::Error),
+    #[error("Invalid JSON scenario")]
+    Json(#[from] serde_json::Error),
+    #[error("Unknown scenario format {0:?}, expected .toml or .json")]
+    UnknownFormat(Option<String>),
+    #[error("Scenario is written for {declared:?}, the simulator speaks {expected:?}")]
+    DeclaredVersionMismatch { declared: ProtocolVersion, expected: ProtocolVersion },
+    #[error("Event {index} (cmd_id {cmd_id:#06x}) uses the {found:?} layout, the simulator speaks {expected:?}")]
+    VersionMismatch { index: usize, cmd_id: u16, expected: ProtocolVersion, found: ProtocolVersion },
+}
+
+/// 布局随赛季变化的消息在 serde 格式中的 `t`，脚本中这些消息的 `c` 不写版本，按 [`Scenario::version`] 解析
+const VERSIONED_MESSAGES: [&str; 4] = ["event_data", "referee_warning", "game_robot_status", "r_f_i_d_status"];
+
+/// 脚本中的一条事件，`at` 为距模拟开始的秒数
+#[derive(Debug, Clone)]
+pub struct ScenarioEvent {
+    pub at: Duration,
+    pub message: Message,
+}
+
+/// 比赛脚本
+///
+/// 消息沿用 [`Message`] 的 serde 格式，例如 TOML 中的
+/// `message = { t = "robot_hurt", c = { hurt_type = "OverHeat", armor_id = 0 } }`。
+/// 事件无需按时间排序，同一时刻的事件按书写顺序发送。
+///
+/// 脚本开头的 `version = "V2024"` 声明协议版本，缺省为 [`ProtocolVersion::V2023`]。
+/// `EventData`、`RefereeWarning`、`GameRobotStatus` 与 `RFIDStatus` 的内容直接按该版本的布局书写，
+/// 不需要 `V2023 = { ... }` 一层，声明的版本须与模拟器一致，见 [`Scenario::validate`]。
+/// `RobotHurt` 只转发给机器人，不改变模拟的血量，扣血需另外写一条 `GameRobotHP`。
+#[derive(Debug, Clone, Default)]
+pub struct Scenario {
+    pub version: ProtocolVersion,
+    pub events: Vec<ScenarioEvent>,
+}
+
+/// 脚本文件中的格式，消息先保留为未解析的值，待读到 `version` 后再补上版本
+#[derive(Serialize, Deserialize)]
+struct RawScenario {
+    #[serde(default)]
+    version: ProtocolVersion,
+    #[serde(default)]
+    events: Vec<RawEvent>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawEvent {
+    #[serde(with = "seconds")]
+    at: Duration,
+    message: Value,
+}
+
+fn is_versioned(message: &Value) -> bool {
+    message.get("t").and_then(Value::as_str).is_some_and(|t| VERSIONED_MESSAGES.contains(&t))
+}
+
+impl<'de> Deserialize<'de> for Scenario {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawScenario::deserialize(deserializer)?;
+        let layout = serde_json::to_value(raw.version.layout()).map_err(de::Error::custom)?;
+        let layout = layout.as_str().unwrap_or_default();
+        let events = raw.events.into_iter().enumerate()
+            .map(|(index, RawEvent { at, mut message })| {
+                if is_versioned(&message) {
+                    if let Some(content) = message.get_mut("c") {
+                        *content = Value::Object([(layout.to_owned(), content.take())].into_iter().collect());
+                    }
+                }
+                let message = serde_json::from_value(message)
+                    .map_err(|err| de::Error::custom(format_args!("event {}: {}", index, err)))?;
+                Ok(ScenarioEvent { at, message })
+            })
+            .collect::<Result<_, D::Error>>()?;
+        Ok(Self { version: raw.version, events })
+    }
+}
+
+impl Serialize for Scenario {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let events = self.events.iter()
+            .map(|event| {
+                let mut message = serde_json::to_value(&event.message).map_err(ser::Error::custom)?;
+                if is_versioned(&message) {
+                    if let Some(Value::Object(content)) = message.get_mut("c") {
+                        if let Some((_, inner)) = std::mem::take(content).into_iter().next() {
+                            message["c"] = inner;
+                        }
+                    }
+                }
+                Ok(RawEvent { at: event.at, message })
+            })
+            .collect::<Result<_, S::Error>>()?;
+        RawScenario { version: self.version, events }.serialize(serializer)
+    }
+}
+
+impl Scenario {
+    pub fn new(version: ProtocolVersion) -> Self {
+        Self { version, events: Vec::new() }
+    }
+
+    pub fn from_toml_str(s: &str) -> Result<Self, ScenarioError> {
+        Ok(toml::from_str(s)?)
+    }
+
+    pub fn from_json_str(s: &str) -> Result<Self, ScenarioError> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    /// 按扩展名读取 `.toml` 或 `.json` 脚本
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&content),
+            Some("json") => Self::from_json_str(&content),
+            other => Err(ScenarioError::UnknownFormat(other.map(str::to_owned))),
+        }
+    }
+
+    /// 检查声明的版本与所有事件的消息布局是否属于 `version`
+    pub fn validate(&self, version: ProtocolVersion) -> Result<(), ScenarioError> {
+        if self.version.layout() != version.layout() {
+            return Err(ScenarioError::DeclaredVersionMismatch { declared: self.version, expected: version });
+        }
+        for (index, event) in self.events.iter().enumerate() {
+            match event.message.version() {
+                Some(found) if found != version.layout() => {
+                    return Err(ScenarioError::VersionMismatch { index, cmd_id: event.message.cmd_id(), expected: version, found });
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, at: Duration, message: Message) -> &mut Self {
+        self.events.push(ScenarioEvent { at, message });
+        self
+    }
+}
+
+mod seconds {
+    use std::time::Duration;
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(at: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(at.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(secs).map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,181 @@
+//! 汇总所有裁判系统消息的比赛状态，与客户端实现无关
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::proto;
+use crate::proto::id::RobotJob;
+use crate::proto::Message;
+
+/// 超过多少个发送周期未更新视为过期
+pub const STALE_PERIODS: u32 = 3;
+
+/// 周期性消息的发送周期，取各赛季协议中较低的频率，非周期性消息返回 `None`
+pub fn expected_period(cmd_id: u16) -> Option<Duration> {
+    let hz = match cmd_id {
+        0x0001 | 0x0003 | 0x0101 => 1,
+        0x0201 | 0x0202 => 10,
+        0x0203 | 0x0204 | 0x0205 | 0x0208 | 0x0209 | 0x020A | 0x020B | 0x020C => 1,
+        _ => return None,
+    };
+    Some(Duration::from_secs(1) / hz)
+}
+
+const HERO: &[u16] = &[
+    0x0001, 0x0002, 0x0003, 0x0101, 0x0102, 0x0104, 0x0201, 0x0202, 0x0203, 0x0204,
+    0x0206, 0x0207, 0x0208, 0x0209, 0x0301, 0x0303, 0x0304,
+];
+/// 工程没有发射机构，不关注热量与射击数据，可使用自定义控制器
+const ENGINEER: &[u16] = &[
+    0x0001, 0x0002, 0x0003, 0x0101, 0x0102, 0x0104, 0x0201, 0x0203, 0x0204, 0x0206,
+    0x0208, 0x0209, 0x0301, 0x0302, 0x0303, 0x0304,
+];
+const INFANTRY: &[u16] = &[
+    0x0001, 0x0002, 0x0003, 0x0101, 0x0102, 0x0104, 0x0201, 0x0202, 0x0203, 0x0204,
+    0x0206, 0x0207, 0x0208, 0x0209, 0x0301, 0x0303, 0x0304,
+];
+const SENTRY: &[u16] = &[
+    0x0001, 0x0002, 0x0003, 0x0101, 0x0102, 0x0104, 0x0201, 0x0202, 0x0203, 0x0204,
+    0x0206, 0x0207, 0x0208, 0x0209, 0x020B, 0x0301, 0x0303,
+];
+const DRONE: &[u16] = &[
+    0x0001, 0x0002, 0x0003, 0x0101, 0x0104, 0x0201, 0x0202, 0x0203, 0x0204, 0x0205,
+    0x0206, 0x0207, 0x0208, 0x0301, 0x0304,
+];
+const DART: &[u16] = &[0x0001, 0x0002, 0x0003, 0x0101, 0x0104, 0x0105, 0x0201, 0x020A, 0x0301];
+const RADAR: &[u16] = &[0x0001, 0x0002, 0x0003, 0x0101, 0x0104, 0x0105, 0x0201, 0x020C, 0x0301];
+
+/// 各兵种预设关注的命令码
+pub fn role_cmd_ids(job: RobotJob) -> &'static [u16] {
+    match job {
+        RobotJob::Hero => HERO,
+        RobotJob::Engineer => ENGINEER,
+        RobotJob::Infantry3 | RobotJob::Infantry4 | RobotJob::Infantry5 => INFANTRY,
+        RobotJob::Sentry => SENTRY,
+        RobotJob::Drone => DRONE,
+        RobotJob::Dart => DART,
+        RobotJob::Radar => RADAR,
+    }
+}
+
+/// 附带最后更新时刻的值
+#[derive(Debug, Clone)]
+pub struct Timestamped<T> {
+    pub value: T,
+    pub updated: Instant,
+}
+
+impl<T> Timestamped<T> {
+    pub fn age(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.updated)
+    }
+
+    pub fn is_stale(&self, now: Instant, max_age: Duration) -> bool {
+        self.age(now) > max_age
+    }
+}
+
+/// 比赛状态快照
+///
+/// 每条下行消息通过 [`RefereeState::update`] 写入对应字段，机器人发往裁判系统的 0x0305、0x0306 与 0x0307 不计入状态。
+/// 0x0301 按发送方分别保存最新一条，尚未收录的命令码按 cmd_id 保存原始数据。
+#[derive(Debug, Clone, Default)]
+pub struct RefereeState {
+    pub game_status: Option<Timestamped<proto::GameStatus>>,
+    pub game_result: Option<Timestamped<proto::Winner>>,
+    /// 红方与蓝方血量
+    pub game_robot_hp: Option<Timestamped<(proto::TeamHP, proto::TeamHP)>>,
+    pub event_data: Option<Timestamped<proto::EventData>>,
+    pub supply_projectile_action: Option<Timestamped<proto::SupplyProjectileAction>>,
+    pub referee_warning: Option<Timestamped<proto::RefereeWarning>>,
+    pub dart_remaining_time: Option<Timestamped<u8>>,
+    pub game_robot_status: Option<Timestamped<proto::GameRobotStatus>>,
+    pub power_heat_data: Option<Timestamped<proto::PowerHeatData>>,
+    pub game_robot_pos: Option<Timestamped<proto::GameRobotPos>>,
+    pub power_rune_buff: Option<Timestamped<proto::PowerRuneBuff>>,
+    pub aerial_robot_energy: Option<Timestamped<u8>>,
+    pub robot_hurt: Option<Timestamped<proto::RobotHurt>>,
+    pub shoot_data: Option<Timestamped<proto::ShootData>>,
+    pub bullet_remaining: Option<Timestamped<proto::BulletRemaining>>,
+    pub rfid_status: Option<Timestamped<proto::RFIDStatus>>,
+    pub dart_client_cmd: Option<Timestamped<proto::DartClientCmd>>,
+    pub ground_robot_position: Option<Timestamped<proto::GroundRobotPosition>>,
+    pub radar_mark_data: Option<Timestamped<proto::RadarMarkData>>,
+    pub custom_controller_data: Option<Timestamped<Vec<u8>>>,
+    pub map_command: Option<Timestamped<proto::MapCommand>>,
+    pub remote_control: Option<Timestamped<proto::RemoteControl>>,
+    /// 以 `send_id` 为键
+    pub interactive_data: BTreeMap<u16, Timestamped<proto::StudentInteractiveData>>,
+    pub unknown: BTreeMap<u16, Timestamped<Vec<u8>>>,
+    updated: BTreeMap<u16, Instant>,
+}
+
+impl RefereeState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, message: Message) {
+        self.update_at(message, Instant::now())
+    }
+
+    pub fn update_at(&mut self, message: Message, updated: Instant) {
+        fn set<T>(field: &mut Option<Timestamped<T>>, value: T, updated: Instant) {
+            *field = Some(Timestamped { value, updated });
+        }
+
+        let cmd_id = message.cmd_id();
+        match message {
+            Message::GameStatus(status) => set(&mut self.game_status, status, updated),
+            Message::GameResult(winner) => set(&mut self.game_result, winner, updated),
+            Message::GameRobotHP { red, blue } => set(&mut self.game_robot_hp, (red, blue), updated),
+            Message::EventData(data) => set(&mut self.event_data, data, updated),
+            Message::SupplyProjectileAction(action) => set(&mut self.supply_projectile_action, action, updated),
+            Message::RefereeWarning(warning) => set(&mut self.referee_warning, warning, updated),
+            Message::DartRemainingTime(time) => set(&mut self.dart_remaining_time, time, updated),
+            Message::GameRobotStatus(status) => set(&mut self.game_robot_status, status, updated),
+            Message::PowerHeatData(data) => set(&mut self.power_heat_data, data, updated),
+            Message::GameRobotPos(pos) => set(&mut self.game_robot_pos, pos, updated),
+            Message::PowerRuneBuff(buff) => set(&mut self.power_rune_buff, buff, updated),
+            Message::AerialRobotEnergy(energy) => set(&mut self.aerial_robot_energy, energy, updated),
+            Message::RobotHurt(hurt) => set(&mut self.robot_hurt, hurt, updated),
+            Message::ShootData(data) => set(&mut self.shoot_data, data, updated),
+            Message::BulletRemaining(remaining) => set(&mut self.bullet_remaining, remaining, updated),
+            Message::RFIDStatus(status) => set(&mut self.rfid_status, status, updated),
+            Message::DartClientCmd(cmd) => set(&mut self.dart_client_cmd, cmd, updated),
+            Message::GroundRobotPosition(position) => set(&mut self.ground_robot_position, position, updated),
+            Message::RadarMarkData(data) => set(&mut self.radar_mark_data, data, updated),
+            Message::CustomControllerInteractiveData(data) => set(&mut self.custom_controller_data, data, updated),
+            Message::MapCommand(command) => set(&mut self.map_command, command, updated),
+            Message::RemoteControl(control) => set(&mut self.remote_control, control, updated),
+            Message::StudentInteractiveData(data) => {
+                self.interactive_data.insert(data.send_id, Timestamped { value: data, updated });
+            }
+            Message::Unknown { cmd_id, payload } => {
+                self.unknown.insert(cmd_id, Timestamped { value: payload, updated });
+            }
+            Message::MinimapReceipt { .. } | Message::CustomClientData(_) | Message::MapSentryData(_) => return,
+        }
+        self.updated.insert(cmd_id, updated);
+    }
+
+    /// 某个命令码最后一次更新的时刻
+    pub fn last_update(&self, cmd_id: u16) -> Option<Instant> {
+        self.updated.get(&cmd_id).copied()
+    }
+
+    /// 收到过但已超过 [`STALE_PERIODS`] 个周期未更新的周期性消息
+    ///
+    /// 从未收到的消息不计入，因为不同兵种只会收到其中一部分。
+    pub fn stale_at(&self, now: Instant) -> Vec<u16> {
+        self.updated.iter()
+            .filter(|(&cmd_id, &updated)| expected_period(cmd_id)
+                .is_some_and(|period| now.saturating_duration_since(updated) > period * STALE_PERIODS))
+            .map(|(&cmd_id, _)| cmd_id)
+            .collect()
+    }
+
+    pub fn stale(&self) -> Vec<u16> {
+        self.stale_at(Instant::now())
+    }
+}
@@ -0,0 +1,129 @@
+//! 接收端链路统计，用于诊断线缆、接插件等造成的丢帧与误码
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::proto;
+use crate::proto::parser::Diagnostic;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CmdStats {
+    pub frames: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LinkStatsSnapshot {
+    /// 统计开始至今的时长
+    pub elapsed: Duration,
+    /// 收到的全部字节，包括被丢弃的
+    pub bytes: u64,
+    pub frames: u64,
+    /// 根据 seq 跳变推算的丢帧数
+    pub lost_frames: u64,
+    pub skipped_bytes: u64,
+    pub crc8_errors: u64,
+    pub crc16_errors: u64,
+    pub oversize_errors: u64,
+    pub decode_errors: u64,
+    pub per_cmd: BTreeMap<u16, CmdStats>,
+}
+
+impl LinkStatsSnapshot {
+    pub fn bytes_per_second(&self) -> f64 {
+        per_second(self.bytes, self.elapsed)
+    }
+
+    /// 指定命令码的平均接收频率，单位 Hz
+    pub fn frame_rate(&self, cmd_id: u16) -> f64 {
+        self.per_cmd.get(&cmd_id).map_or(0.0, |cmd| per_second(cmd.frames, self.elapsed))
+    }
+
+    pub fn crc_errors(&self) -> u64 {
+        self.crc8_errors + self.crc16_errors
+    }
+
+    /// 丢帧占应收帧数的比例
+    pub fn loss_ratio(&self) -> f64 {
+        let expected = self.frames + self.lost_frames;
+        if expected == 0 { 0.0 } else { self.lost_frames as f64 / expected as f64 }
+    }
+}
+
+fn per_second(count: u64, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 { count as f64 / secs } else { 0.0 }
+}
+
+/// 链路统计器，由读取端在每次解析出帧或诊断信息时调用
+///
+/// seq 与上一帧相同时视为发送端未递增 seq，不计入丢帧。
+#[derive(Debug)]
+pub struct LinkStats {
+    started: Instant,
+    last_seq: Option<u8>,
+    snapshot: LinkStatsSnapshot,
+}
+
+impl Default for LinkStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinkStats {
+    pub fn new() -> Self {
+        Self { started: Instant::now(), last_seq: None, snapshot: LinkStatsSnapshot::default() }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn record_frame(&mut self, frame: &proto::Frame2) {
+        let size = (frame.data_length as usize + proto::FRAME_OVERHEAD) as u64;
+        let stats = &mut self.snapshot;
+        stats.frames += 1;
+        stats.bytes += size;
+        let cmd = stats.per_cmd.entry(frame.message.cmd_id()).or_default();
+        cmd.frames += 1;
+        cmd.bytes += size;
+        if let Some(last_seq) = self.last_seq {
+            if frame.seq != last_seq {
+                stats.lost_frames += frame.seq.wrapping_sub(last_seq).wrapping_sub(1) as u64;
+            }
+        }
+        self.last_seq = Some(frame.seq);
+    }
+
+    pub fn record_diagnostic(&mut self, diagnostic: &Diagnostic) {
+        let stats = &mut self.snapshot;
+        // 帧头或 CRC16 校验失败时解析器只丢弃 SOF 一个字节
+        match diagnostic {
+            Diagnostic::Skipped(skipped) => {
+                stats.skipped_bytes += skipped.len() as u64;
+                stats.bytes += skipped.len() as u64;
+            }
+            Diagnostic::Crc8Mismatch { .. } => {
+                stats.crc8_errors += 1;
+                stats.bytes += 1;
+            }
+            Diagnostic::DataLengthTooLarge { .. } => {
+                stats.oversize_errors += 1;
+                stats.bytes += 1;
+            }
+            Diagnostic::Crc16Mismatch { .. } => {
+                stats.crc16_errors += 1;
+                stats.bytes += 1;
+            }
+            Diagnostic::Decode { frame, .. } => {
+                stats.decode_errors += 1;
+                stats.bytes += frame.len() as u64;
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> LinkStatsSnapshot {
+        LinkStatsSnapshot { elapsed: self.started.elapsed(), ..self.snapshot.clone() }
+    }
+}
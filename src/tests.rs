@@ -5,3 +5,790 @@ use super::*;
 //     let result = add(2, 2);
 //     assert_eq!(result, 4);
 // }
+
+#[test]
+fn link_stats_seq_gap() {
+    let mut stats = stats::LinkStats::new();
+    for seq in [0, 1, 3, 3, 7] {
+        let frame = proto::Frame2::new(seq, proto::Message::DartRemainingTime(5)).unwrap();
+        stats.record_frame(&frame);
+    }
+    stats.record_diagnostic(&proto::parser::Diagnostic::Skipped(vec![0x00; 4]));
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.frames, 5);
+    assert_eq!(snapshot.lost_frames, 4);
+    assert_eq!(snapshot.skipped_bytes, 4);
+    assert_eq!(snapshot.bytes, 5 * 10 + 4);
+    assert_eq!(snapshot.per_cmd[&0x0105].frames, 5);
+}
+
+#[cfg(feature = "tokio_client")]
+#[tokio::test]
+async fn tokio_client_over_duplex() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let ((mut reader, mut writer), mut peer) = tokio_client::duplex(1024, proto::ProtocolVersion::V2023);
+    let bytes = proto::Frame2::encode(7, proto::Message::DartRemainingTime(12)).unwrap();
+    peer.write_all(&[0x00]).await.unwrap();
+    peer.write_all(&bytes).await.unwrap();
+    assert!(matches!(
+        reader.recv().await.unwrap(),
+        Err(tokio_client::codec::RefereeCodecError::Diagnostic(proto::parser::Diagnostic::Skipped(skipped))) if skipped == [0x00]
+    ));
+    let frame = reader.recv().await.unwrap().unwrap();
+    assert!(matches!(frame.message, proto::Message::DartRemainingTime(12)));
+
+    assert!(matches!(
+        writer.send_message_with_known_data_length(proto::Message::DartRemainingTime(1), 2).await,
+        Err(tokio_client::codec::RefereeCodecError::Frame(proto::FrameError::DataLengthMismatch { declared: 2, actual: 1 }))
+    ));
+    writer.send_minimap_receipt(101, (14.0, 7.5)).await.unwrap();
+    let mut sent = [0u8; 9 + 10];
+    peer.read_exact(&mut sent).await.unwrap();
+    assert!(matches!(proto::Frame2::decode(&sent).unwrap().message, proto::Message::MinimapReceipt { target_robot_id: 101, .. }));
+}
+
+#[cfg(feature = "tokio_client")]
+#[tokio::test]
+async fn tokio_client_versioned_round_trip() {
+    let ((_reader, mut writer), peer) = tokio_client::duplex(1024, proto::ProtocolVersion::V2024);
+    let (mut wire, _) = tokio_client::connect_io(peer, proto::ProtocolVersion::V2024);
+
+    let status = proto::v2024::GameRobotStatus {
+        robot_id: 3,
+        robot_level: 2,
+        remain_hp: 150,
+        max_hp: 200,
+        shooter_barrel_cooling_value: 40,
+        shooter_barrel_heat_limit: 240,
+        chassis_power_limit: 100,
+        mains_power_gimbal_output: true,
+        mains_power_chassis_output: true,
+        mains_power_shooter_output: false,
+    };
+    let rfid = proto::v2024::RFIDStatus { own_ring_highland: true, central_buff_point: true, ..Default::default() };
+    writer.send_message(proto::Message::GameRobotStatus(proto::GameRobotStatus::V2024(status))).await.unwrap();
+    writer.send_message(proto::Message::RFIDStatus(proto::RFIDStatus::V2024(rfid))).await.unwrap();
+
+    let frame = wire.recv().await.unwrap().unwrap();
+    assert_eq!(frame.data_length, 13);
+    let proto::Message::GameRobotStatus(proto::GameRobotStatus::V2024(status)) = frame.message else {
+        panic!("expected 2024 GameRobotStatus, got {:?}", frame.message)
+    };
+    assert_eq!((status.robot_id, status.shooter_barrel_heat_limit, status.chassis_power_limit), (3, 240, 100));
+    assert!(status.mains_power_chassis_output && !status.mains_power_shooter_output);
+
+    let frame = wire.recv().await.unwrap().unwrap();
+    assert_eq!(frame.data_length, 4);
+    let proto::Message::RFIDStatus(proto::RFIDStatus::V2024(rfid)) = frame.message else {
+        panic!("expected 2024 RFIDStatus, got {:?}", frame.message)
+    };
+    assert!(rfid.own_ring_highland && rfid.central_buff_point && !rfid.base_gain_zone);
+}
+
+#[cfg(feature = "simulator")]
+#[tokio::test]
+async fn simulator_full_match() {
+    use std::time::Duration;
+    use simulator::{MatchTimeline, RefereeSimulator, SimulatorConfig, UplinkError, UplinkReport};
+
+    let config = SimulatorConfig {
+        robot_id: 9,
+        timeline: MatchTimeline {
+            pre_competition: Duration::ZERO,
+            setup: Duration::ZERO,
+            initialization: Duration::ZERO,
+            countdown: Duration::ZERO,
+            combat: Duration::from_millis(300),
+            calculating: Duration::ZERO,
+            winner: proto::Winner::Red,
+        },
+        ..Default::default()
+    };
+    let (simulator, mut reports) = RefereeSimulator::new(config);
+    let (robot, referee) = tokio::io::duplex(4096);
+    let referee = tokio::spawn(simulator.run(referee));
+    let (mut reader, mut writer) = tokio_client::connect_io(robot, proto::ProtocolVersion::V2023);
+
+    writer.send_minimap_receipt(101, (14.0, 7.5)).await.unwrap();
+    writer.send_minimap_receipt(3, (14.0, 7.5)).await.unwrap();
+
+    let mut in_combat = false;
+    loop {
+        match reader.recv().await.unwrap().unwrap().message {
+            proto::Message::GameStatus(status) => {
+                in_combat |= matches!(status.game_progress, proto::GameProgress::InCombat);
+            }
+            proto::Message::GameResult(winner) => {
+                assert!(matches!(winner, proto::Winner::Red));
+                break;
+            }
+            _ => {}
+        }
+    }
+    assert!(in_combat);
+    referee.await.unwrap().unwrap();
+
+    assert!(matches!(reports.recv().await.unwrap(), UplinkReport::Accepted(_)));
+    assert!(matches!(reports.recv().await.unwrap(), UplinkReport::Rejected { reason: UplinkError::InvalidTarget(3), .. }));
+}
+
+#[cfg(feature = "simulator")]
+#[tokio::test]
+async fn simulator_scenario() {
+    use std::time::Duration;
+    use simulator::{RefereeSimulator, Scenario, SimulatorConfig};
+
+    let text = include_str!("../scenarios/overheat_card_shield.toml");
+    let example = Scenario::from_toml_str(text).unwrap();
+    assert_eq!(example.events.len(), 6);
+    assert!(matches!(example.events[3].message, proto::Message::RefereeWarning(proto::RefereeWarning::V2023(_))));
+    example.validate(proto::ProtocolVersion::V2023).unwrap();
+    assert!(matches!(
+        example.validate(proto::ProtocolVersion::V2024),
+        Err(simulator::ScenarioError::DeclaredVersionMismatch { declared: proto::ProtocolVersion::V2023, .. })
+    ));
+    // 2023 赛季的内容不能按 2024 赛季的布局解析
+    assert!(Scenario::from_toml_str(&text.replace(r#"version = "V2023""#, r#"version = "V2024""#)).is_err());
+    // 序列化时同样省略版本
+    let serialized = toml::to_string(&example).unwrap();
+    assert!(!serialized.contains("V2023 ="));
+    assert_eq!(Scenario::from_toml_str(&serialized).unwrap().events.len(), 6);
+    let (v2024, _reports) = RefereeSimulator::new(SimulatorConfig { version: proto::ProtocolVersion::V2024, ..Default::default() });
+    assert!(v2024.with_scenario(example).is_err());
+
+    let v2024 = Scenario::from_json_str(r#"{ "version": "V2025", "events": [
+        { "at": 1.0, "message": { "t": "referee_warning", "c": { "level": "YellowCard", "offending_robot_id": 3, "count": 1 } } }
+    ] }"#).unwrap();
+    assert!(matches!(v2024.events[0].message, proto::Message::RefereeWarning(proto::RefereeWarning::V2024(_))));
+    v2024.validate(proto::ProtocolVersion::V2024).unwrap();
+    let rfid = serde_json::to_string(&Scenario {
+        version: proto::ProtocolVersion::V2024,
+        events: vec![simulator::ScenarioEvent { at: Duration::ZERO, message: proto::Message::RFIDStatus(proto::RFIDStatus::V2024(Default::default())) }],
+    }).unwrap();
+    assert!(matches!(Scenario::from_json_str(&rfid).unwrap().events[0].message, proto::Message::RFIDStatus(proto::RFIDStatus::V2024(_))));
+
+    let scenario = Scenario::from_json_str(r#"{ "events": [
+        { "at": 0.1, "message": { "t": "game_result", "c": "Blue" } },
+        { "at": 0.05, "message": { "t": "robot_hurt", "c": { "hurt_type": "OverHeat", "armor_id": 0 } } }
+    ] }"#).unwrap();
+    let (simulator, _reports) = RefereeSimulator::new(SimulatorConfig::default());
+    let (robot, referee) = tokio::io::duplex(4096);
+    let referee = tokio::spawn(simulator.with_scenario(scenario).unwrap().run(referee));
+    let (mut reader, _writer) = tokio_client::connect_io(robot, proto::ProtocolVersion::V2023);
+
+    let mut hurt = false;
+    loop {
+        match reader.recv().await.unwrap().unwrap().message {
+            proto::Message::RobotHurt(proto::RobotHurt { hurt_type, .. }) => {
+                assert_eq!(hurt_type, proto::HurtType::OverHeat);
+                hurt = true;
+            }
+            proto::Message::GameResult(winner) => {
+                assert!(matches!(winner, proto::Winner::Blue));
+                break;
+            }
+            _ => {}
+        }
+    }
+    assert!(hurt);
+    referee.await.unwrap().unwrap();
+}
+
+#[test]
+fn record_replay_round_trip() {
+    use std::time::{Duration, Instant};
+    use std::io::Write;
+    use record::{FlushPolicy, RecordReader, Recorder, Replayer, ReplaySpeed};
+
+    let mut recorder = Recorder::new(Vec::new());
+    recorder.record(&[0x00, 0x11]).unwrap();
+    recorder.record(&[]).unwrap();
+    let frame = proto::Frame2::new(1, proto::Message::DartRemainingTime(5)).unwrap();
+    recorder.record_frame(&frame).unwrap();
+    let file = recorder.into_inner();
+    let text = String::from_utf8(file.clone()).unwrap();
+    assert_eq!(text.lines().count(), 2);
+
+    let records: Vec<_> = RecordReader::new(&file[..]).collect::<Result<_, _>>().unwrap();
+    assert_eq!(records[0].bytes, [0x00, 0x11]);
+    assert_eq!(records[1].bytes, frame.to_wire_bytes().unwrap());
+    assert!(records[0].at <= records[1].at);
+
+    let log = "# comment\n0 a5\n200000 0001\n";
+    let start = Instant::now();
+    let mut out = Vec::new();
+    Replayer::new(RecordReader::new(log.as_bytes()), ReplaySpeed::Scaled(10.0)).replay(&mut out).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    assert_eq!(out, [0xA5, 0x00, 0x01]);
+
+    let mut stepped = Replayer::new(RecordReader::new("1 zz\n".as_bytes()), ReplaySpeed::Stepped);
+    assert!(matches!(stepped.next(), Some(Err(record::RecordError::Hex { line: 1, .. }))));
+
+    // 未到刷新间隔时记录留在缓冲区中，finish 时写出
+    let mut buffered = Recorder::new(std::io::BufWriter::new(Vec::new())).flush_policy(FlushPolicy::Interval(Duration::from_secs(3600)));
+    buffered.record(&[0xA5]).unwrap();
+    buffered.record(&[0x00]).unwrap();
+    let mut buffered = buffered.into_inner();
+    assert!(buffered.get_ref().is_empty());
+    buffered.flush().unwrap();
+    assert_eq!(buffered.get_ref().split(|&byte| byte == b'\n').filter(|line| !line.is_empty()).count(), 2);
+
+    let mut durable = Recorder::new(std::io::BufWriter::new(Vec::new())).flush_policy(FlushPolicy::EveryRecord);
+    durable.record(&[0xA5]).unwrap();
+    assert!(!durable.into_inner().get_ref().is_empty());
+
+    let mut finished = Recorder::new(std::io::BufWriter::new(Vec::new())).flush_policy(FlushPolicy::Interval(Duration::from_secs(3600)));
+    finished.record(&[0xA5]).unwrap();
+    assert!(!finished.finish().unwrap().get_ref().is_empty());
+}
+
+#[test]
+fn referee_state_staleness() {
+    use std::time::{Duration, Instant};
+
+    let mut state = state::RefereeState::new();
+    let start = Instant::now();
+    state.update_at(proto::Message::PowerHeatData(Default::default()), start);
+    state.update_at(proto::Message::RobotHurt(proto::RobotHurt { hurt_type: proto::HurtType::OverHeat, armor_id: 0 }), start);
+    state.update_at(proto::Message::MinimapReceipt { target_robot_id: 101, target_position: (1.0, 1.0) }, start);
+    state.update_at(proto::Message::GameStatus(Default::default()), start + Duration::from_secs(1));
+
+    assert!(state.power_heat_data.is_some());
+    assert_eq!(state.robot_hurt.as_ref().unwrap().value.hurt_type, proto::HurtType::OverHeat);
+    assert_eq!(state.last_update(0x0305), None);
+    assert_eq!(state.last_update(0x0001), Some(start + Duration::from_secs(1)));
+    assert_eq!(state.stale_at(start + Duration::from_millis(200)), Vec::<u16>::new());
+    assert_eq!(state.stale_at(start + Duration::from_millis(400)), vec![0x0202]);
+    assert_eq!(state.stale_at(start + Duration::from_secs(5)), vec![0x0001, 0x0202]);
+}
+
+#[test]
+fn role_cmd_ids_per_job() {
+    use proto::id::RobotJob;
+
+    let engineer = state::role_cmd_ids(RobotJob::Engineer);
+    assert!(!engineer.contains(&0x0202) && !engineer.contains(&0x0207));
+    assert!(engineer.contains(&0x0302));
+    assert!(state::role_cmd_ids(RobotJob::Hero).contains(&0x0207));
+    assert_eq!(state::role_cmd_ids(RobotJob::Infantry3), state::role_cmd_ids(RobotJob::Infantry5));
+}
+
+#[cfg(feature = "tokio_client")]
+#[tokio::test]
+async fn tokio_client_role_watch() {
+    use tokio::io::AsyncWriteExt;
+
+    let ((reader, _writer), mut peer) = tokio_client::duplex(1024, proto::ProtocolVersion::V2023);
+    let watch = reader.watch_role(proto::id::RobotJob::Infantry3);
+    let mut shared = watch.clone();
+
+    let radar = proto::Message::RadarMarkData(Default::default());
+    let status = proto::Message::GameRobotStatus(proto::GameRobotStatus::V2023(proto::v2023::GameRobotStatus {
+        robot_id: 3,
+        ..Default::default()
+    }));
+    peer.write_all(&proto::Frame2::encode(0, radar).unwrap()).await.unwrap();
+    peer.write_all(&proto::Frame2::encode(1, status).unwrap()).await.unwrap();
+
+    assert_eq!(shared.get_game_robot_status().await.unwrap().robot_id(), 3);
+    assert!(watch.snapshot().radar_mark_data.is_none());
+    assert!(watch.is_running());
+
+    watch.stop().await.unwrap();
+    assert!(shared.get_game_status().await.is_none());
+    assert!(!shared.is_running());
+}
+
+#[test]
+fn message_filter() {
+    use hub::MessageFilter;
+    use proto::MessageKind;
+
+    let hurt = proto::Message::RobotHurt(proto::RobotHurt { hurt_type: proto::HurtType::ArmorHit, armor_id: 1 });
+    let p2p = proto::Message::StudentInteractiveData(proto::StudentInteractiveData {
+        content_id: 0x0201,
+        send_id: 3,
+        receive_id: 4,
+        content: proto::StudentInteractiveDataType::PeerToPeerCommunication { content_id: 0x0201, content: vec![0; 4] },
+    });
+    assert_eq!(hurt.kind(), MessageKind::RobotHurt);
+    assert!(MessageFilter::All.matches(&hurt));
+    assert!(MessageFilter::kind(MessageKind::RobotHurt).matches(&hurt));
+    assert!(!MessageFilter::kind(MessageKind::RobotHurt).matches(&p2p));
+    assert!(MessageFilter::interactive(0x0201).matches(&p2p));
+    assert!(!MessageFilter::interactive(0x0202).matches(&p2p));
+    let either = MessageFilter::interactive(0x0202).or(MessageFilter::kind(MessageKind::RobotHurt));
+    assert!(either.matches(&hurt) && !either.matches(&p2p));
+    assert!(MessageFilter::custom(|message| message.cmd_id() == 0x0301).matches(&p2p));
+}
+
+#[cfg(feature = "tokio_client")]
+#[tokio::test]
+async fn tokio_client_hub_fan_out() {
+    use tokio::io::AsyncWriteExt;
+    use hub::{Lagged, MessageFilter};
+
+    let ((reader, _writer), mut peer) = tokio_client::duplex(1024, proto::ProtocolVersion::V2023);
+    let hub = reader.into_hub(2);
+    let mut all = hub.subscribe(MessageFilter::All);
+    let mut darts = hub.subscribe(MessageFilter::kind(proto::MessageKind::DartRemainingTime));
+
+    for seq in 0..4 {
+        peer.write_all(&proto::Frame2::encode(seq, proto::Message::AerialRobotEnergy(seq)).unwrap()).await.unwrap();
+    }
+    peer.write_all(&proto::Frame2::encode(4, proto::Message::DartRemainingTime(9)).unwrap()).await.unwrap();
+    drop(peer);
+
+    assert!(matches!(darts.recv().await, Some(Err(Lagged(3)))));
+    assert!(matches!(darts.recv().await.unwrap().unwrap().message, proto::Message::DartRemainingTime(9)));
+    assert!(matches!(all.recv().await, Some(Err(Lagged(3)))));
+    assert!(matches!(all.recv().await.unwrap().unwrap().message, proto::Message::AerialRobotEnergy(3)));
+    assert!(matches!(all.recv().await.unwrap().unwrap().message, proto::Message::DartRemainingTime(9)));
+    hub.stop().await.unwrap();
+    assert!(all.recv().await.is_none());
+}
+
+#[test]
+fn send_queue_limits() {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+    use send_queue::{Priority, SendQueue, SendQueueConfig, SendQueueError};
+
+    let receipt = |id| proto::Message::MinimapReceipt { target_robot_id: id, target_position: (1.0, 1.0) };
+    let start = Instant::now();
+
+    let mut queue = SendQueue::new_at(SendQueueConfig::default(), start);
+    queue.push(receipt(101), Priority::Normal).unwrap();
+    queue.push(receipt(102), Priority::Normal).unwrap();
+    queue.push(proto::Message::DartRemainingTime(1), Priority::Low).unwrap();
+    queue.push(proto::Message::DartRemainingTime(2), Priority::High).unwrap();
+    assert!(matches!(queue.pop(start), Some(proto::Message::DartRemainingTime(2))));
+    assert!(matches!(queue.pop(start), Some(proto::Message::MinimapReceipt { target_robot_id: 101, .. })));
+    // 0x0305 受 10 Hz 限制，低优先级的消息先发
+    assert!(matches!(queue.pop(start), Some(proto::Message::DartRemainingTime(1))));
+    assert!(queue.pop(start).is_none());
+    assert_eq!(queue.next_ready(start), Some(start + Duration::from_millis(100)));
+    assert!(matches!(queue.pop(start + Duration::from_millis(100)), Some(proto::Message::MinimapReceipt { target_robot_id: 102, .. })));
+    assert!(queue.is_empty());
+
+    let config = SendQueueConfig { bytes_per_second: 190, burst_bytes: 0, cmd_rate_limits: HashMap::new(), capacity: 8 };
+    let mut queue = SendQueue::new_at(config, start);
+    for id in 0..8 {
+        queue.push(receipt(id), Priority::Normal).unwrap();
+    }
+    queue.push_coalesced(receipt(200), Priority::Normal, 7).unwrap();
+    queue.push_coalesced(receipt(201), Priority::Normal, 7).unwrap();
+    assert_eq!((queue.len(), queue.dropped()), (8, 1));
+    let sent = std::iter::from_fn(|| queue.pop(start)).count();
+    assert_eq!(sent, 6);
+    assert_eq!(queue.next_ready(start), Some(start + Duration::from_secs_f64(5.0 / 190.0)));
+
+    let config = SendQueueConfig { capacity: 2, ..Default::default() };
+    let mut queue = SendQueue::new_at(config, start);
+    queue.push(proto::Message::DartRemainingTime(1), Priority::Low).unwrap();
+    queue.push_coalesced(proto::Message::DartRemainingTime(2), Priority::High, 0).unwrap();
+    queue.push_coalesced(proto::Message::DartRemainingTime(3), Priority::Low, 0).unwrap();
+    assert!(matches!(queue.push(proto::Message::DartRemainingTime(4), Priority::High), Ok(())));
+    assert!(matches!(queue.push(proto::Message::DartRemainingTime(5), Priority::Low), Err(SendQueueError::Full)));
+    assert_eq!(queue.dropped(), 2);
+    assert!(matches!(queue.pop(start), Some(proto::Message::DartRemainingTime(3))));
+    assert!(matches!(queue.pop(start), Some(proto::Message::DartRemainingTime(4))));
+}
+
+#[cfg(any(feature = "blocking_client", feature = "tokio_client"))]
+#[test]
+fn serial_backoff_and_selector() {
+    use std::time::Duration;
+    use serial::{Backoff, PortSelector};
+
+    let backoff = Backoff::default();
+    assert_eq!(backoff.delay(0), Duration::from_millis(100));
+    assert_eq!(backoff.delay(3), Duration::from_millis(800));
+    assert_eq!(backoff.delay(100), Duration::from_secs(5));
+
+    assert_eq!(PortSelector::from("/dev/ttyUSB0").resolve().unwrap(), "/dev/ttyUSB0");
+    let usb = PortSelector::Usb { vid: 0x1a86, pid: 0x7523, serial_number: None };
+    assert_eq!(usb.to_string(), "USB 1a86:7523");
+}
+
+#[cfg(any(feature = "blocking_client", feature = "tokio_client"))]
+#[test]
+fn serial_config_builder() {
+    use std::time::Duration;
+    use serial::{FlowControl, SerialConfig};
+
+    let config = SerialConfig::default();
+    assert_eq!(config.baud_rate, 115200);
+    assert_eq!(config.timeout, Duration::from_secs(1));
+
+    let config = SerialConfig::new()
+        .baud_rate(921600)
+        .flow_control(FlowControl::Hardware)
+        .timeout(Duration::from_millis(50))
+        .read_buffer_size(0);
+    assert_eq!(config.baud_rate, 921600);
+    assert_eq!(config.flow_control, FlowControl::Hardware);
+    assert_eq!(config.read_buffer_size, 1);
+}
+
+#[test]
+fn ui_manager_diff() {
+    use proto::graphic::{GraphicAddOperation, GraphicColor, GraphicData};
+    use proto::{Message, StudentInteractiveDataType};
+    use proto::graphic::GraphicDeleteOperation;
+    use ui::UiManager;
+
+    /// 生成更新并视为全部送达
+    fn send(ui: &mut UiManager) -> Vec<StudentInteractiveDataType> {
+        let updates = ui.updates();
+        updates.iter().for_each(|update| ui.confirm(update));
+        contents(updates.into_iter().map(|update| update.message).collect())
+    }
+
+    fn contents(messages: Vec<Message>) -> Vec<StudentInteractiveDataType> {
+        messages.into_iter().map(|message| match message {
+            Message::StudentInteractiveData(data) => {
+                assert_eq!((data.send_id, data.receive_id), (3, 0x0103));
+                assert_eq!(data.content_id, data.get_content_id());
+                data.content
+            }
+            other => panic!("Unexpected message {:?}", other),
+        }).collect()
+    }
+
+    let mut ui = UiManager::new(3);
+    for (name, y) in [(b"l1", 400), (b"l2", 500), (b"l3", 600)] {
+        ui.set(GraphicData::line(name, 1, GraphicColor::Green).from(900, y).to(1020, y).width(2).build().unwrap()).unwrap();
+    }
+    let mut text = [0u8; 30];
+    text[..3].copy_from_slice(b"CAP");
+    ui.set_text(GraphicData::character(b"cap", 2, GraphicColor::White).at(100, 800).font_size(20).length(3).build().unwrap(), text).unwrap();
+    assert!(ui.set_text(GraphicData::circle(b"c", 0, GraphicColor::Cyan).build().unwrap(), text).is_err());
+
+    assert_eq!(ui.updates().len(), 2);
+    assert_eq!(ui.updates().len(), 2, "unconfirmed updates are generated again");
+    let contents_added = send(&mut ui);
+    assert_eq!(contents_added.len(), 2);
+    match &contents_added[0] {
+        StudentInteractiveDataType::GraphicDraw5(graphics) => {
+            let operations: Vec<_> = graphics.iter().map(|graphic| graphic.operate_type).collect();
+            assert_eq!(operations, [GraphicAddOperation::Add, GraphicAddOperation::Add, GraphicAddOperation::Add, GraphicAddOperation::Nop, GraphicAddOperation::Nop]);
+        }
+        other => panic!("Unexpected content {:?}", other),
+    }
+    assert!(matches!(&contents_added[1], StudentInteractiveDataType::GraphicDrawCharacter((graphic, content)) if graphic.graphic_name == *b"cap" && content == &text));
+    assert!(ui.updates().is_empty());
+
+    ui.set(GraphicData::line(b"l1", 1, GraphicColor::Yellow).from(900, 400).to(1020, 400).width(2).build().unwrap()).unwrap();
+    assert!(ui.remove(b"l3"));
+    let updates = ui.updates();
+    match contents(updates.iter().map(|update| update.message.clone()).collect()).as_slice() {
+        [StudentInteractiveDataType::GraphicDraw2([deleted, modified])] => {
+            assert_eq!((deleted.graphic_name, deleted.operate_type), (*b"l3\0", GraphicAddOperation::Delete));
+            assert_eq!((modified.graphic_name, modified.operate_type), (*b"l1\0", GraphicAddOperation::Modify));
+        }
+        other => panic!("Unexpected contents {:?}", other),
+    }
+
+    // 发送失败时删除不会丢失
+    assert_eq!(ui.updates().len(), 1);
+    ui.confirm(&updates[0]);
+    assert!(ui.updates().is_empty());
+
+    ui.invalidate();
+    match send(&mut ui).as_slice() {
+        [StudentInteractiveDataType::GraphicDelete { operate_type: GraphicDeleteOperation::DeleteAll, .. }, StudentInteractiveDataType::GraphicDraw2(graphics), StudentInteractiveDataType::GraphicDrawCharacter(_)] => {
+            assert!(graphics.iter().all(|graphic| graphic.operate_type == GraphicAddOperation::Add));
+        }
+        other => panic!("Unexpected contents {:?}", other),
+    }
+    assert!(ui.updates().is_empty());
+}
+
+#[test]
+fn ui_allocator_leases() {
+    use proto::graphic::{GraphicColor, GraphicData, GraphicDeleteOperation};
+    use proto::{Message, StudentInteractiveDataType};
+    use ui::{AllocError, UiAllocator, UiManager};
+
+    let mut allocator = UiAllocator::new();
+    let aim = allocator.lease().unwrap();
+    let ammo = allocator.lease().unwrap();
+    assert_eq!((aim.layer(), ammo.layer()), (0, 1));
+    assert_eq!(allocator.lease_layer(1), Err(AllocError::LayerInUse(1)));
+    assert_eq!(allocator.lease_layer(10), Err(AllocError::InvalidLayer(10)));
+
+    let first = allocator.name(aim).unwrap();
+    let second = allocator.name(ammo).unwrap();
+    assert_ne!(first, second);
+    assert_eq!(allocator.names(aim).unwrap(), [first]);
+
+    let mut ui = UiManager::new(1);
+    ui.set(GraphicData::circle(first, aim.layer(), GraphicColor::Green).center(960, 540).radius(20).build().unwrap()).unwrap();
+    ui.set(GraphicData::integer(second, ammo.layer(), GraphicColor::White).value(120).build().unwrap()).unwrap();
+    let updates = ui.updates();
+    assert_eq!(updates.len(), 1);
+    updates.iter().for_each(|update| ui.confirm(update));
+
+    ui.release(&mut allocator, aim).unwrap();
+    assert!(ui.get(first).is_none());
+    let updates = ui.updates();
+    match updates.as_slice() {
+        [update] => match &update.message {
+            Message::StudentInteractiveData(data) => assert!(matches!(
+                data.content,
+                StudentInteractiveDataType::GraphicDelete { operate_type: GraphicDeleteOperation::DeleteOne, layer: 0 }
+            )),
+            other => panic!("Unexpected message {:?}", other),
+        },
+        other => panic!("Unexpected updates {:?}", other),
+    }
+    ui.confirm(&updates[0]);
+    assert!(ui.updates().is_empty());
+    assert_eq!(allocator.name(aim), Err(AllocError::Released));
+    assert_eq!(allocator.free_layers().next(), Some(0));
+
+    let reused = allocator.lease().unwrap();
+    assert_eq!(reused.layer(), 0);
+    assert_eq!(allocator.name(reused).unwrap(), first);
+}
+
+#[test]
+fn ui_text_lines() {
+    use proto::graphic::{GraphicColor, GraphicEnum};
+    use ui::text::{draw_text, draw_text_lines, draw_text_truncated, TextError, TEXT_CAPACITY};
+
+    let status = "CAP 87% | AMMO 120";
+    let (graphic, content) = draw_text(b"st", 0, GraphicColor::White, (100, 800), 20, status).unwrap();
+    assert!(matches!(graphic.graphic_data, GraphicEnum::Character(data) if data.decimal_digit == 18 && data.font_size == 20 && data.width == 2));
+    assert_eq!(&content[..status.len()], status.as_bytes());
+    assert!(content[status.len()..].iter().all(|&byte| byte == 0));
+
+    let long = "0123456789".repeat(4);
+    assert_eq!(draw_text(b"l", 0, GraphicColor::White, (0, 0), 20, &long).unwrap_err(), TextError::TooLong(40));
+    let (_, content) = draw_text_truncated(b"l", 0, GraphicColor::White, (0, 0), 20, &long).unwrap();
+    assert_eq!(&content[..], &long.as_bytes()[..TEXT_CAPACITY]);
+
+    let text = "CAP 87% | AMMO 120 | HEAT 30/240\nOK";
+    let names = [*b"t0\0", *b"t1\0", *b"t2\0"];
+    let lines = draw_text_lines(names, 1, GraphicColor::Yellow, (50, 700), 20, text).unwrap();
+    let rendered: Vec<(&[u8], u16)> = lines.iter()
+        .map(|(graphic, content)| match &graphic.graphic_data {
+            GraphicEnum::Character(data) => (&content[..data.decimal_digit as usize], data.y),
+            other => panic!("Unexpected graphic {:?}", other),
+        })
+        .collect();
+    assert_eq!(rendered, [(&b"CAP 87% | AMMO 120 | HEAT"[..], 700), (&b"30/240"[..], 660), (&b"OK"[..], 620)]);
+
+    assert_eq!(
+        draw_text_lines([*b"t0\0"], 1, GraphicColor::Yellow, (50, 700), 20, text).unwrap_err(),
+        TextError::NotEnoughNames { needed: 3, given: 1 },
+    );
+    assert_eq!(draw_text_lines(names, 1, GraphicColor::Yellow, (50, 10), 20, "a\nb").unwrap_err(), TextError::OffScreen(1));
+}
+
+#[test]
+fn ui_render_svg() {
+    use proto::graphic::{GraphicColor, GraphicData};
+    use proto::id::Side;
+    use ui::render::UiPreview;
+    use ui::text::draw_text;
+    use ui::UiManager;
+
+    let mut ui = UiManager::new(103);
+    ui.set(GraphicData::line(b"l", 0, GraphicColor::RedAndBlue).from(0, 0).to(1920, 1080).width(3).build().unwrap()).unwrap();
+    ui.set(GraphicData::rectangle(b"r", 1, GraphicColor::Yellow).from(100, 200).to(300, 100).width(2).build().unwrap()).unwrap();
+    ui.set(GraphicData::arc(b"a", 1, GraphicColor::Cyan).center(960, 540).half_axes(100, 100).angles(0, 90).width(2).build().unwrap()).unwrap();
+    ui.set(GraphicData::floating_number(b"f", 2, GraphicColor::White).at(10, 50).font_size(20).decimal_digit(1).value(12.34).build().unwrap()).unwrap();
+    let (graphic, text) = draw_text(b"t", 2, GraphicColor::Green, (10, 1000), 20, "A<B").unwrap();
+    ui.set_text(graphic, text).unwrap();
+
+    let mut preview = UiPreview::new(Side::Blue);
+    preview.background = None;
+    fn send(ui: &mut UiManager, preview: &mut UiPreview) {
+        for update in ui.updates() {
+            preview.apply_message(&update.message);
+            ui.confirm(&update);
+        }
+    }
+
+    send(&mut ui, &mut preview);
+    assert_eq!(preview.len(), 5);
+    let svg = preview.to_svg();
+    assert!(svg.contains(r##"<line x1="0" y1="1080" x2="1920" y2="0" fill="none" stroke="#30a0ff" stroke-width="3"/>"##));
+    assert!(svg.contains(r#"<rect x="100" y="880" width="200" height="100""#));
+    assert!(svg.contains(r#"<path d="M 960.00 440.00 A 100 100 0 0 1 1060.00 540.00""#));
+    assert!(svg.contains(">12.3</text>"));
+    assert!(svg.contains(r##"<text x="10" y="100" font-size="20" font-family="monospace" fill="#00ff00">A&lt;B</text>"##));
+
+    ui.remove(b"l");
+    send(&mut ui, &mut preview);
+    assert!(!preview.to_svg().contains("<line"));
+    ui.delete_layer(1);
+    send(&mut ui, &mut preview);
+    assert_eq!(preview.len(), 2);
+}
+
+#[cfg(feature = "blocking_client")]
+#[test]
+fn blocking_hub_disconnects() {
+    use std::time::Duration;
+    use blocking_client::RefereeHub;
+    use hub::MessageFilter;
+
+    let (input, receiver) = crossbeam_channel::unbounded();
+    let hub = RefereeHub::spawn(receiver, 4);
+    let subscription = hub.subscribe(MessageFilter::All);
+    input.send(Ok(proto::Frame2::new(0, proto::Message::DartRemainingTime(7)).unwrap())).unwrap();
+    drop(input);
+
+    assert!(matches!(subscription.recv_timeout(Duration::from_secs(1)).unwrap().unwrap().message, proto::Message::DartRemainingTime(7)));
+    assert!(subscription.recv().is_none());
+    assert!(hub.is_closed());
+    assert_eq!(hub.subscriber_count(), 0);
+    assert!(hub.subscribe(MessageFilter::All).recv().is_none());
+}
+
+#[cfg(all(feature = "blocking_client", unix))]
+#[test]
+fn blocking_client_reconnects() {
+    use std::io::Write;
+    use std::os::unix::fs::symlink;
+    use std::time::{Duration, Instant};
+    use blocking_client::RefereeClient;
+    use serial::{Backoff, ConnectionState, SerialConfig};
+    use serialport::{SerialPort, TTYPort};
+
+    /// 伪终端的主端与从端路径
+    fn pty() -> (TTYPort, String) {
+        let (master, slave) = TTYPort::pair().unwrap();
+        (master, slave.name().unwrap())
+    }
+
+    fn wait_for(client: &RefereeClient, expected: impl Fn(&ConnectionState) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let state = client.connection_state();
+            if expected(&state) { return; }
+            assert!(Instant::now() < deadline, "Stuck in {:?}", state);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    // 通过符号链接模拟重新插拔后出现的新设备
+    let link = std::env::temp_dir().join(format!("rmreco-reconnect-{}", std::process::id()));
+    let link_path = link.to_str().unwrap().to_owned();
+    let _ = std::fs::remove_file(&link);
+    let (master, path) = pty();
+    symlink(&path, &link).unwrap();
+
+    let config = SerialConfig::default().timeout(Duration::from_millis(50)).version(proto::ProtocolVersion::V2023);
+    let mut client = RefereeClient::try_new_with_config(link_path.as_str(), config.clone()).unwrap();
+    client.enable_reconnect(Backoff { initial: Duration::from_millis(20), max: Duration::from_millis(20), factor: 1 });
+    let receiver = client.spawn_read_thread().unwrap();
+    assert_eq!(client.connection_state(), ConnectionState::Connected(link_path.clone()));
+
+    drop(master);
+    wait_for(&client, |state| matches!(state, ConnectionState::Disconnected(_)));
+    assert!(client.send_message(proto::Message::DartRemainingTime(1)).is_err());
+
+    let (mut master, path) = pty();
+    std::fs::remove_file(&link).unwrap();
+    symlink(&path, &link).unwrap();
+    wait_for(&client, |state| state == &ConnectionState::Connected(link_path.clone()));
+    // 诊断信息不会出现在 spawn_read_thread 的接收端
+    master.write_all(&[0x00]).unwrap();
+    master.write_all(&proto::Frame2::encode(0, proto::Message::DartRemainingTime(9)).unwrap()).unwrap();
+    let frame = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(matches!(frame.message, proto::Message::DartRemainingTime(9)));
+    drop(client);
+    drop(master);
+
+    // 停止时不必等完退避间隔
+    let (master, path) = pty();
+    std::fs::remove_file(&link).unwrap();
+    symlink(&path, &link).unwrap();
+    let mut client = RefereeClient::try_new_with_config(link_path.as_str(), config).unwrap();
+    client.enable_reconnect(Backoff { initial: Duration::from_secs(30), max: Duration::from_secs(30), factor: 1 });
+    let _receiver = client.spawn_read_thread_with_diagnostics().unwrap();
+    drop(master);
+    wait_for(&client, |state| matches!(state, ConnectionState::Disconnected(_)));
+    let stopping = Instant::now();
+    drop(client);
+    assert!(stopping.elapsed() < Duration::from_secs(2));
+    std::fs::remove_file(&link).unwrap();
+}
+
+#[cfg(feature = "tokio_client")]
+#[tokio::test]
+async fn tokio_client_supervised_disconnected() {
+    use std::io;
+    use std::time::Duration;
+    use serial::{Backoff, ConnectionState, SerialConfig};
+    use tokio_client::codec::RefereeCodecError;
+
+    let path = std::env::temp_dir().join(format!("rmreco-missing-{}", std::process::id()));
+    let backoff = Backoff { initial: Duration::from_millis(20), max: Duration::from_millis(20), factor: 1 };
+    let (_reader, mut writer, mut state) = tokio_client::connect_supervised(path.to_str().unwrap(), SerialConfig::default(), backoff);
+    state.wait_for(|state| matches!(state, ConnectionState::Disconnected(_))).await.unwrap();
+
+    // 超过发送缓存的容量也不会阻塞
+    for _ in 0..32 {
+        let result = tokio::time::timeout(Duration::from_secs(1), writer.send_message(proto::Message::DartRemainingTime(1))).await.unwrap();
+        assert!(matches!(result, Err(RefereeCodecError::Io(err)) if err.kind() == io::ErrorKind::NotConnected));
+    }
+}
+
+#[cfg(feature = "tokio_client")]
+#[tokio::test]
+async fn tokio_client_queued_writer() {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+    use send_queue::{Priority, SendQueueConfig};
+
+    let ((_reader, writer), peer) = tokio_client::duplex(1024, proto::ProtocolVersion::V2023);
+    let (mut wire, _) = tokio_client::connect_io(peer, proto::ProtocolVersion::V2023);
+    let queued = writer.into_queued(SendQueueConfig {
+        cmd_rate_limits: HashMap::from([(0x0305, 10.0)]),
+        ..Default::default()
+    });
+    let receipt = |target_robot_id| proto::Message::MinimapReceipt { target_robot_id, target_position: (1.0, 1.0) };
+    queued.send(receipt(101), Priority::Low).unwrap();
+    queued.send(receipt(102), Priority::Low).unwrap();
+    queued.send(proto::Message::StudentInteractiveData(proto::StudentInteractiveData {
+        content_id: 0x0200,
+        send_id: 3,
+        receive_id: 4,
+        content: proto::StudentInteractiveDataType::PeerToPeerCommunication { content_id: 0x0200, content: vec![1, 2, 3] },
+    }), Priority::High).unwrap();
+    assert_eq!(queued.pending(), 3);
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        let frame = tokio::time::timeout(Duration::from_secs(1), wire.recv()).await.unwrap().unwrap().unwrap();
+        received.push((frame.seq, frame.message, Instant::now()));
+    }
+    assert!(matches!(received[0], (0, proto::Message::StudentInteractiveData(_), _)));
+    assert!(matches!(received[1], (1, proto::Message::MinimapReceipt { target_robot_id: 101, .. }, _)));
+    assert!(matches!(received[2], (2, proto::Message::MinimapReceipt { target_robot_id: 102, .. }, _)));
+    // 0x0305 限制为 10 Hz
+    assert!(received[2].2 - received[1].2 >= Duration::from_millis(80));
+    assert_eq!((queued.pending(), queued.dropped()), (0, 0));
+}
+
+#[cfg(feature = "tokio_client")]
+#[tokio::test]
+async fn tokio_client_hub_closes_on_eof() {
+    use hub::MessageFilter;
+
+    let ((reader, _writer), peer) = tokio_client::duplex(1024, proto::ProtocolVersion::V2023);
+    let hub = reader.into_hub(4);
+    let mut subscription = hub.subscribe(MessageFilter::All);
+    drop(peer);
+
+    let closed = tokio::time::timeout(std::time::Duration::from_secs(1), subscription.recv()).await;
+    assert!(closed.unwrap().is_none());
+    assert_eq!(hub.subscriber_count(), 0);
+    assert!(hub.subscribe(MessageFilter::All).recv().await.is_none());
+}
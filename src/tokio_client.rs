@@ -1,31 +1,198 @@
-use futures_util::{SinkExt, StreamExt};
-use futures_util::stream::{SplitSink, SplitStream};
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use futures_util::{future, Sink, SinkExt, Stream, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream};
+use tokio::net::{TcpStream, ToSocketAddrs, UdpSocket};
 use tokio::select;
-use tokio::sync::{mpsc, watch};
-use tokio_serial::{SerialPortBuilderExt, SerialStream};
-use tokio_util::codec::{Decoder, Framed};
-use tracing::{debug, error, Instrument};
+use tokio::sync::{broadcast, mpsc, watch, Notify};
+use tokio_serial::SerialPortBuilderExt;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::codec::Decoder;
+use tokio_util::sync::{CancellationToken, DropGuard, PollSender};
+use tokio_util::udp::UdpFramed;
+use tracing::{debug, info, trace, warn, Instrument};
 
+use crate::hub::{Lagged, MessageFilter};
 use crate::proto;
+use crate::proto::id::RobotJob;
+use crate::serial::{Backoff, ConnectionState, PortSelector, SerialConfig};
+use crate::send_queue::{Priority, SendQueue, SendQueueConfig, SendQueueError};
+use crate::state::{self, RefereeState};
+use crate::stats::{LinkStats, LinkStatsSnapshot};
+
+type FrameStream = Pin<Box<dyn Stream<Item = Result<proto::Frame2, codec::RefereeCodecError>> + Send>>;
+type FrameSink = Pin<Box<dyn Sink<proto::Frame2, Error = codec::RefereeCodecError> + Send>>;
 
 pub fn connect(path: &str) -> anyhow::Result<(RefereeClientReader, RefereeClientWriter)> {
-    let serial_stream = tokio_serial::new(path, 115200).open_native_async()?;
-    let framed = codec::RefereeCodec.framed(serial_stream);
-    let (sink, stream) = framed.split();
-    let (client, writer) = (RefereeClientReader { stream }, RefereeClientWriter { sink, seq: 0 });
-    Ok((client, writer))
+    connect_with_config(path, &SerialConfig::default())
+}
+
+/// 按 `config` 中的波特率、流控、协议版本等参数打开串口，`timeout` 与 `read_buffer_size` 对异步读取无效
+pub fn connect_with_config(selector: impl Into<PortSelector>, config: &SerialConfig) -> anyhow::Result<(RefereeClientReader, RefereeClientWriter)> {
+    let path = selector.into().resolve()?;
+    let serial_stream = config.builder(&path).open_native_async()?;
+    Ok(connect_io(serial_stream, config.version))
+}
+
+/// 按 `config` 打开串口并在断开后按 `backoff` 自动重连，返回的读写端在重连前后保持可用，`seq` 也继续递增
+///
+/// 断开、等待重连期间发送返回 [`io::ErrorKind::NotConnected`]，断开前尚未写出的帧被丢弃，不会在重连后补发。读取端被丢弃后不再重连。
+pub fn connect_supervised(
+    selector: impl Into<PortSelector>,
+    config: SerialConfig,
+    backoff: Backoff,
+) -> (RefereeClientReader, RefereeClientWriter, watch::Receiver<ConnectionState>) {
+    let selector = selector.into();
+    let stats = Arc::new(Mutex::new(LinkStats::new()));
+    let (frame_tx, frame_rx) = mpsc::channel(64);
+    let (out_tx, out_rx) = mpsc::channel(16);
+    let (state_tx, state) = watch::channel(ConnectionState::Connecting);
+    let span = tracing::info_span!("supervisor", %selector);
+    tokio::spawn(supervise(selector, config, backoff, stats.clone(), frame_tx, out_rx, state_tx).instrument(span));
+    let sink = PollSender::new(out_tx)
+        .sink_map_err(|_| codec::RefereeCodecError::Io(io::ErrorKind::BrokenPipe.into()));
+    (
+        RefereeClientReader { stream: Box::pin(ReceiverStream::new(frame_rx)), stats },
+        RefereeClientWriter { sink: Box::pin(sink), seq: 0, connection: Some(state.clone()) },
+        state,
+    )
+}
+
+async fn supervise(
+    selector: PortSelector,
+    config: SerialConfig,
+    backoff: Backoff,
+    stats: Arc<Mutex<LinkStats>>,
+    frame_tx: mpsc::Sender<Result<proto::Frame2, codec::RefereeCodecError>>,
+    mut out_rx: mpsc::Receiver<proto::Frame2>,
+    state_tx: watch::Sender<ConnectionState>,
+) {
+    let mut attempt = 0;
+    let mut writer_open = true;
+    'supervise: while !frame_tx.is_closed() {
+        let opened = selector.resolve()
+            .and_then(|path| config.builder(&path).open_native_async().map(|port| (path, port)));
+        let (path, port) = match opened {
+            Ok(opened) => opened,
+            Err(err) => {
+                let delay = backoff.delay(attempt);
+                attempt = attempt.saturating_add(1);
+                debug!("Failed to open serial port: {}, retrying in {:?}", err, delay);
+                state_tx.send_replace(ConnectionState::Disconnected(err.to_string()));
+                let sleep = tokio::time::sleep(delay);
+                tokio::pin!(sleep);
+                loop {
+                    select! {
+                        _ = &mut sleep => continue 'supervise,
+                        _ = frame_tx.closed() => break 'supervise,
+                        frame = out_rx.recv(), if writer_open => match frame {
+                            Some(frame) => trace!("Dropping frame while disconnected: {:?}", frame),
+                            None => writer_open = false,
+                        },
+                    }
+                }
+            }
+        };
+        attempt = 0;
+        info!("Connected to {}", path);
+        state_tx.send_replace(ConnectionState::Connected(path));
+
+        let codec = codec::RefereeCodec::with_stats(config.version, stats.clone());
+        let (mut sink, mut stream) = codec.framed(port).split();
+        let reason = loop {
+            select! {
+                item = stream.next() => match item {
+                    Some(Ok(item)) => if frame_tx.send(item).await.is_err() { break "Reader dropped".to_owned() },
+                    Some(Err(err)) => {
+                        let reason = err.to_string();
+                        let _ = frame_tx.send(Err(err)).await;
+                        break reason;
+                    }
+                    None => break "Unexpected EOF".to_owned(),
+                },
+                frame = out_rx.recv(), if writer_open => match frame {
+                    Some(frame) => if let Err(err) = sink.send(frame).await { break err.to_string() },
+                    None => writer_open = false,
+                },
+                _ = frame_tx.closed() => break "Reader dropped".to_owned(),
+            }
+        };
+        warn!("Disconnected: {}", reason);
+        state_tx.send_replace(ConnectionState::Disconnected(reason));
+        // 断开前已进入缓存的帧不再发出
+        while let Ok(frame) = out_rx.try_recv() {
+            trace!("Dropping frame while disconnected: {:?}", frame);
+        }
+    }
+    state_tx.send_replace(ConnectionState::Closed);
+}
+
+/// 在任意双向字节流上建立连接，例如 PTY、Unix socket 或测试用的内存管道
+pub fn connect_io<T>(io: T, version: proto::ProtocolVersion) -> (RefereeClientReader, RefereeClientWriter)
+    where T: AsyncRead + AsyncWrite + Send + 'static {
+    let codec = codec::RefereeCodec::new(version);
+    let stats = codec.stats();
+    let (sink, stream) = codec.framed(io).split();
+    let stream = stream.map(|item| item.and_then(|frame| frame));
+    (RefereeClientReader { stream: Box::pin(stream), stats }, RefereeClientWriter { sink: Box::pin(sink), seq: 0, connection: None })
+}
+
+/// 连接 ser2net 一类的 TCP 串口桥
+pub async fn connect_tcp(addr: impl ToSocketAddrs, version: proto::ProtocolVersion) -> anyhow::Result<(RefereeClientReader, RefereeClientWriter)> {
+    let stream = TcpStream::connect(addr).await?;
+    stream.set_nodelay(true)?;
+    Ok(connect_io(stream, version))
+}
+
+/// 通过 UDP 与 `remote` 通信，每个数据报应包含完整的帧，跨数据报的残帧以 [`codec::RefereeCodecError::Truncated`] 返回
+pub async fn connect_udp(local: impl ToSocketAddrs, remote: SocketAddr, version: proto::ProtocolVersion) -> anyhow::Result<(RefereeClientReader, RefereeClientWriter)> {
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(remote).await?;
+    let codec = codec::RefereeCodec::new(version);
+    let stats = codec.stats();
+    let (sink, stream) = UdpFramed::new(socket, codec).split();
+    let sink = sink.with(move |frame| future::ready(Ok::<_, codec::RefereeCodecError>((frame, remote))));
+    let stream = stream.map(|item| item.and_then(|(frame, _)| frame));
+    Ok((RefereeClientReader { stream: Box::pin(stream), stats }, RefereeClientWriter { sink: Box::pin(sink), seq: 0, connection: None }))
+}
+
+/// 建立一对内存管道，返回客户端与另一端的字节流，供模拟器或测试使用
+pub fn duplex(max_buf_size: usize, version: proto::ProtocolVersion) -> ((RefereeClientReader, RefereeClientWriter), DuplexStream) {
+    let (client, peer) = tokio::io::duplex(max_buf_size);
+    (connect_io(client, version), peer)
 }
 
 pub struct RefereeClientWriter {
-    sink: SplitSink<Framed<SerialStream, codec::RefereeCodec>, proto::Frame2>,
+    sink: FrameSink,
     seq: u8,
+    /// 仅 [`connect_supervised`] 返回的写入端会检查连接状态
+    connection: Option<watch::Receiver<ConnectionState>>,
 }
 
 impl RefereeClientWriter {
+    /// 转为按裁判系统带宽限制排队发送的 [`QueuedWriter`]
+    pub fn into_queued(self, config: SendQueueConfig) -> QueuedWriter {
+        QueuedWriter::spawn(self, config)
+    }
+
+    /// 发送消息，`data_length` 由编码结果自动计算
+    pub async fn send_message(&mut self, message: proto::Message) -> Result<(), codec::RefereeCodecError> {
+        let data_length = message.data_length()?;
+        self.send_message_with_known_data_length(message, data_length).await
+    }
+
+    /// 自动重连的写入端在串口断开、等待重连期间返回 [`io::ErrorKind::NotConnected`]，帧不会被缓存重发
     pub async fn send_message_with_known_data_length(
         &mut self,
         message: proto::Message, data_length: u16,
     ) -> Result<(), codec::RefereeCodecError> {
+        if let Some(ConnectionState::Disconnected(reason)) = self.connection.as_ref().map(|state| state.borrow().clone()) {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, reason).into());
+        }
         let frame = proto::Frame2 {
             data_length,
             seq: self.seq,
@@ -42,16 +209,15 @@ impl RefereeClientWriter {
     ///
     /// 不论雷达站属于哪一方，都以小地图左下角（红方补给区）为原点，双方对向为第一维，范围为 `(0.0..28.0, 0.0..15.0)`
     pub async fn send_minimap_receipt(&mut self, target_robot_id: u16, target_position: (f32, f32)) -> Result<(), codec::RefereeCodecError> {
-        self.send_message_with_known_data_length(proto::Message::MinimapReceipt {
+        self.send_message(proto::Message::MinimapReceipt {
             target_robot_id,
             target_position,
-        }, 10).await
+        }).await
     }
 
     /// 机器人之间通信
     pub async fn send_p2p(&mut self, content_id: u16, send_id: u16, receive_id: u16, content: Vec<u8>) -> Result<(), codec::RefereeCodecError> {
-        let data_length = (content.len() + 6) as u16;
-        self.send_message_with_known_data_length(proto::Message::StudentInteractiveData(
+        self.send_message(proto::Message::StudentInteractiveData(
             proto::StudentInteractiveData {
                 content_id,
                 send_id,
@@ -61,178 +227,414 @@ impl RefereeClientWriter {
                     content,
                 },
             }
-        ), data_length).await
+        )).await
+    }
+}
+
+/// 经过 [`SendQueue`] 限速后由后台任务发送的写入端，可克隆后在多个任务中使用
+///
+/// 所有克隆都被丢弃后后台任务结束，仍在排队的消息被丢弃。
+#[derive(Clone)]
+pub struct QueuedWriter {
+    queue: Arc<Mutex<SendQueue>>,
+    notify: Arc<Notify>,
+    _guard: Arc<DropGuard>,
+}
+
+impl fmt::Debug for QueuedWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueuedWriter").field("queue", &self.queue).finish_non_exhaustive()
+    }
+}
+
+impl QueuedWriter {
+    pub fn spawn(mut writer: RefereeClientWriter, config: SendQueueConfig) -> Self {
+        let queue = Arc::new(Mutex::new(SendQueue::new(config)));
+        let notify = Arc::new(Notify::new());
+        let cancel = CancellationToken::new();
+        let task_queue = queue.clone();
+        let task_notify = notify.clone();
+        let task_cancel = cancel.clone();
+        tokio::spawn(async move {
+            loop {
+                let now = tokio::time::Instant::now();
+                let (message, next_ready) = {
+                    let mut queue = task_queue.lock().unwrap();
+                    (queue.pop(now.into_std()), queue.next_ready(now.into_std()))
+                };
+                if let Some(message) = message {
+                    match writer.send_message(message).await {
+                        Ok(()) => continue,
+                        Err(codec::RefereeCodecError::Io(err)) if err.kind() == io::ErrorKind::NotConnected => {
+                            debug!("Dropping queued message: {}", err);
+                            continue;
+                        }
+                        Err(codec::RefereeCodecError::Io(err)) => {
+                            warn!("Stopping queued writer: {}", err);
+                            break;
+                        }
+                        Err(err) => {
+                            warn!("Failed to send queued message: {}", err);
+                            continue;
+                        }
+                    }
+                }
+                let sleep = async {
+                    match next_ready {
+                        Some(instant) => tokio::time::sleep_until(instant.into()).await,
+                        None => future::pending().await,
+                    }
+                };
+                select! {
+                    _ = task_notify.notified() => {}
+                    _ = sleep => {}
+                    _ = task_cancel.cancelled() => break,
+                }
+            }
+        }.instrument(tracing::info_span!("queued_writer")));
+        Self { queue, notify, _guard: Arc::new(cancel.drop_guard()) }
+    }
+    pub fn send(&self, message: proto::Message, priority: Priority) -> Result<(), SendQueueError> {
+        self.queue.lock().unwrap().push(message, priority)?;
+        self.notify.notify_one();
+        Ok(())
+    }
+    /// 替换仍在排队的同 cmd_id、同 `key` 的旧消息，见 [`SendQueue::push_coalesced`]
+    pub fn send_coalesced(&self, message: proto::Message, priority: Priority, key: u64) -> Result<(), SendQueueError> {
+        self.queue.lock().unwrap().push_coalesced(message, priority, key)?;
+        self.notify.notify_one();
+        Ok(())
+    }
+    pub fn pending(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+    pub fn dropped(&self) -> u64 {
+        self.queue.lock().unwrap().dropped()
     }
 }
 
-#[derive(Debug)]
 pub struct RefereeClientReader {
-    stream: SplitStream<Framed<SerialStream, codec::RefereeCodec>>,
+    stream: FrameStream,
+    stats: Arc<Mutex<LinkStats>>,
+}
+
+impl fmt::Debug for RefereeClientReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefereeClientReader").field("stats", &self.stats).finish_non_exhaustive()
+    }
 }
 
 impl RefereeClientReader {
+    /// 接收下一帧，损坏或无法解码的数据以对应的 [`codec::RefereeCodecError`] 变体返回，之后仍可继续接收
     pub async fn recv(&mut self) -> Option<Result<proto::Frame2, codec::RefereeCodecError>> {
         self.stream.next().await
     }
+    /// 当前链路统计，见 [`LinkStatsSnapshot`]
+    pub fn link_stats(&self) -> LinkStatsSnapshot {
+        self.stats.lock().unwrap().snapshot()
+    }
+    /// 在后台把收到的所有消息汇总为 [`RefereeState`]，连接断开或所有接收端被丢弃后停止
+    pub fn watch_state(self) -> watch::Receiver<RefereeState> {
+        let (state_tx, state) = watch::channel(RefereeState::new());
+        tokio::spawn(fold_state(self, state_tx, None, CancellationToken::new())
+            .instrument(tracing::info_span!("watch_state")));
+        state
+    }
+    /// 按兵种预设只汇总 [`state::role_cmd_ids`] 中的消息
+    pub fn watch_role(self, job: RobotJob) -> RefereeClientReaderWatch {
+        RefereeClientReaderWatch::spawn(self, job)
+    }
+    /// 同 `watch_role(RobotJob::Radar)`
     pub async fn watch_radar(self) -> RefereeClientReaderWatch {
-        RefereeClientReaderWatch::spawn_radar(self).await
+        self.watch_role(RobotJob::Radar)
+    }
+    /// 转为可多方订阅的 [`RefereeHub`]，每个订阅者最多缓存 `capacity` 帧
+    pub fn into_hub(self, capacity: usize) -> RefereeHub {
+        RefereeHub::spawn(self, capacity)
     }
 }
 
+async fn fold_state(
+    mut reader: RefereeClientReader,
+    state_tx: watch::Sender<RefereeState>,
+    cmd_ids: Option<&'static [u16]>,
+    cancel: CancellationToken,
+) {
+    loop {
+        let frame = select! {
+            frame = reader.recv() => match frame {
+                Some(frame) => frame,
+                None => break,
+            },
+            _ = state_tx.closed() => break,
+            _ = cancel.cancelled() => break,
+        };
+        match frame {
+            Ok(frame) if cmd_ids.is_none_or(|cmd_ids| cmd_ids.contains(&frame.message.cmd_id())) => {
+                state_tx.send_modify(|state| state.update(frame.message));
+            }
+            Ok(frame) => trace!("Ignored message: {:?}", frame.message),
+            Err(err) => warn!("Error while receiving frame: {}", err),
+        }
+    }
+}
+
+/// 按兵种预设汇总比赛状态的后台任务句柄
+///
+/// 句柄可以任意克隆并分发给云台、底盘、UI 等任务，所有句柄都被丢弃或调用 [`RefereeClientReaderWatch::stop`] 后任务结束。
+/// `get_*` 方法等待对应数据首次到达，任务结束时返回 `None`。
+#[derive(Clone)]
 pub struct RefereeClientReaderWatch {
-    join_handle: tokio::task::JoinHandle<()>,
-    game_robot_hp: watch::Receiver<Option<(proto::TeamHP, proto::TeamHP)>>,
-    game_robot_status: watch::Receiver<Option<proto::GameRobotStatus>>,
-    game_status: watch::Receiver<Option<proto::GameStatus>>,
-    radar_mark_data: watch::Receiver<Option<proto::RadarMarkData>>,
-    event_data: watch::Receiver<Option<proto::EventData>>,
-    stop_signal: mpsc::Sender<()>,
+    job: RobotJob,
+    state: watch::Receiver<RefereeState>,
+    cancel: CancellationToken,
+    join_handle: Arc<tokio::sync::Mutex<Option<tokio::task::JoinHandle<()>>>>,
+}
+
+impl fmt::Debug for RefereeClientReaderWatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefereeClientReaderWatch").field("job", &self.job).finish_non_exhaustive()
+    }
 }
 
 impl RefereeClientReaderWatch {
-    async fn stop(self) -> Result<(), tokio::task::JoinError> {
-        self.stop_signal.send(()).await.unwrap();
-        self.join_handle.await
-    }
-    async fn spawn_radar(mut reader: RefereeClientReader) -> Self {
-        let (game_robot_hp_tx, game_robot_hp) = watch::channel(None);
-        let (game_robot_status_tx, game_robot_status) = watch::channel(None);
-        let (game_status_tx, game_status) = watch::channel(None);
-        let (radar_mark_data_tx, radar_mark_data) = watch::channel(None);
-        let (event_data_tx, event_data) = watch::channel(None);
-        let (stop_signal, mut stop_signal_rx) = mpsc::channel(1);
+    pub fn spawn(reader: RefereeClientReader, job: RobotJob) -> Self {
+        let (state_tx, state) = watch::channel(RefereeState::new());
+        let cancel = CancellationToken::new();
+        let join_handle = tokio::spawn(fold_state(reader, state_tx, Some(state::role_cmd_ids(job)), cancel.clone())
+            .instrument(tracing::info_span!("watch_role", ?job)));
+        Self { job, state, cancel, join_handle: Arc::new(tokio::sync::Mutex::new(Some(join_handle))) }
+    }
+    pub fn job(&self) -> RobotJob {
+        self.job
+    }
+    /// 停止后台任务并等待其结束，对任意一个句柄调用即可
+    pub async fn stop(&self) -> Result<(), tokio::task::JoinError> {
+        self.cancel.cancel();
+        match self.join_handle.lock().await.take() {
+            Some(join_handle) => join_handle.await,
+            None => Ok(()),
+        }
+    }
+    pub fn is_running(&self) -> bool {
+        self.state.has_changed().is_ok()
+    }
+    /// 当前状态的副本
+    pub fn snapshot(&self) -> RefereeState {
+        self.state.borrow().clone()
+    }
+    /// 等待状态更新，任务结束时返回 `false`
+    pub async fn changed(&mut self) -> bool {
+        self.state.changed().await.is_ok()
+    }
+    /// 等待 `f` 从状态中取出值，任务结束时仍未取到则返回 `None`
+    pub async fn wait_for<T>(&mut self, mut f: impl FnMut(&RefereeState) -> Option<T>) -> Option<T> {
+        let mut found = None;
+        let _ = self.state.wait_for(|state| {
+            found = f(state);
+            found.is_some()
+        }).await;
+        found
+    }
+    pub async fn get_game_robot_hp(&mut self) -> Option<(proto::TeamHP, proto::TeamHP)> {
+        self.wait_for(|state| state.game_robot_hp.as_ref().map(|hp| hp.value.clone())).await
+    }
+    pub async fn get_game_robot_status(&mut self) -> Option<proto::GameRobotStatus> {
+        self.wait_for(|state| state.game_robot_status.as_ref().map(|status| status.value.clone())).await
+    }
+    pub async fn get_game_status(&mut self) -> Option<proto::GameStatus> {
+        self.wait_for(|state| state.game_status.as_ref().map(|status| status.value.clone())).await
+    }
+    pub async fn get_radar_mark_data(&mut self) -> Option<proto::RadarMarkData> {
+        self.wait_for(|state| state.radar_mark_data.as_ref().map(|data| data.value.clone())).await
+    }
+    pub async fn get_event_data(&mut self) -> Option<proto::EventData> {
+        self.wait_for(|state| state.event_data.as_ref().map(|data| data.value.clone())).await
+    }
+    pub async fn get_power_heat_data(&mut self) -> Option<proto::PowerHeatData> {
+        self.wait_for(|state| state.power_heat_data.as_ref().map(|data| data.value.clone())).await
+    }
+    pub async fn get_game_robot_pos(&mut self) -> Option<proto::GameRobotPos> {
+        self.wait_for(|state| state.game_robot_pos.as_ref().map(|pos| pos.value.clone())).await
+    }
+    pub async fn get_rfid_status(&mut self) -> Option<proto::RFIDStatus> {
+        self.wait_for(|state| state.rfid_status.as_ref().map(|status| status.value.clone())).await
+    }
+    pub async fn get_power_rune_buff(&mut self) -> Option<proto::PowerRuneBuff> {
+        self.wait_for(|state| state.power_rune_buff.as_ref().map(|buff| buff.value.clone())).await
+    }
+    pub async fn get_dart_remaining_time(&mut self) -> Option<u8> {
+        self.wait_for(|state| state.dart_remaining_time.as_ref().map(|time| time.value)).await
+    }
+}
+
+/// 把一个 [`RefereeClientReader`] 收到的帧分发给多个订阅者
+///
+/// 损坏的数据只记录日志与链路统计，不分发给订阅者。读取端结束、hub 被丢弃或调用 [`RefereeHub::stop`] 后后台任务结束，
+/// 订阅者取完剩余的帧后收到 `None`。
+#[derive(Debug)]
+pub struct RefereeHub {
+    /// 发送端只由后台任务持有，任务结束时通道随之关闭
+    sender: std::sync::Weak<broadcast::Sender<Arc<proto::Frame2>>>,
+    cancel: CancellationToken,
+    join_handle: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl RefereeHub {
+    pub fn spawn(mut reader: RefereeClientReader, capacity: usize) -> Self {
+        let (task_sender, _) = broadcast::channel(capacity);
+        let task_sender = Arc::new(task_sender);
+        let sender = Arc::downgrade(&task_sender);
+        let cancel = CancellationToken::new();
+        let task_cancel = cancel.clone();
         let join_handle = tokio::spawn(async move {
             loop {
                 let frame = select! {
-                    Some(frame) = reader.recv() => frame,
-                    _ = stop_signal_rx.recv() => { break; }
-                    else => { break; }
+                    frame = reader.recv() => match frame {
+                        Some(frame) => frame,
+                        None => break,
+                    },
+                    _ = task_cancel.cancelled() => break,
                 };
                 match frame {
-                    Ok(frame) => match frame.message {
-                        proto::Message::GameRobotHP { red, blue } => { game_robot_hp_tx.send_replace(Some((red, blue))); }
-                        proto::Message::GameRobotStatus(status) => { game_robot_status_tx.send_replace(Some(status)); }
-                        proto::Message::GameStatus(status) => { game_status_tx.send_replace(Some(status)); }
-                        proto::Message::RadarMarkData(data) => { radar_mark_data_tx.send_replace(Some(data)); }
-                        proto::Message::EventData(data) => { event_data_tx.send_replace(Some(data)); }
-                        proto::Message::DartRemainingTime(_) |
-                        proto::Message::GameRobotPos { .. } |
-                        proto::Message::RFIDStatus { .. } |
-                        proto::Message::PowerRuneBuff { .. } |
-                        proto::Message::PowerHeatData { .. } => {}
-                        _ => {
-                            debug!("Unhandled message: {:?}", frame.message);
-                        }
-                    },
-                    Err(err) => {
-                        error!("Error while receiving frame: {}", err);
-                    }
+                    // 没有订阅者时直接丢弃
+                    Ok(frame) => { let _ = task_sender.send(Arc::new(frame)); }
+                    Err(err) => warn!("Error while receiving frame: {}", err),
                 }
             }
-        }.instrument(tracing::info_span!("watch_radar")));
-        Self {
-            join_handle,
-            stop_signal,
-            game_robot_hp,
-            game_robot_status,
-            game_status,
-            radar_mark_data,
-            event_data,
-        }
+        }.instrument(tracing::info_span!("hub")));
+        Self { sender, cancel, join_handle: Some(join_handle) }
     }
-    pub async fn get_game_robot_hp(&mut self) -> (proto::TeamHP, proto::TeamHP) {
-        self.game_robot_hp.wait_for(Option::is_some).await.unwrap().clone().unwrap()
+    /// 后台任务已结束时返回的订阅立即收到 `None`
+    pub fn subscribe(&self, filter: MessageFilter) -> HubSubscription {
+        let receiver = match self.sender.upgrade() {
+            Some(sender) => sender.subscribe(),
+            None => broadcast::channel(1).1,
+        };
+        HubSubscription { receiver, filter }
     }
-    pub async fn get_game_robot_status(&mut self) -> proto::GameRobotStatus {
-        self.game_robot_status.wait_for(Option::is_some).await.unwrap().clone().unwrap()
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.upgrade().map_or(0, |sender| sender.receiver_count())
     }
-    pub async fn get_game_status(&mut self) -> proto::GameStatus {
-        self.game_status.wait_for(Option::is_some).await.unwrap().clone().unwrap()
+    pub async fn stop(mut self) -> Result<(), tokio::task::JoinError> {
+        self.cancel.cancel();
+        match self.join_handle.take() {
+            Some(join_handle) => join_handle.await,
+            None => Ok(()),
+        }
     }
-    pub async fn get_radar_mark_data(&mut self) -> proto::RadarMarkData {
-        self.radar_mark_data.wait_for(Option::is_some).await.unwrap().clone().unwrap()
+}
+
+impl Drop for RefereeHub {
+    fn drop(&mut self) {
+        self.cancel.cancel();
     }
-    pub async fn get_event_data(&mut self) -> proto::EventData {
-        self.event_data.wait_for(Option::is_some).await.unwrap().clone().unwrap()
+}
+
+#[derive(Debug)]
+pub struct HubSubscription {
+    receiver: broadcast::Receiver<Arc<proto::Frame2>>,
+    filter: MessageFilter,
+}
+
+impl HubSubscription {
+    /// 接收下一个满足过滤条件的帧，处理过慢而丢帧时先返回一次 [`Lagged`]
+    pub async fn recv(&mut self) -> Option<Result<Arc<proto::Frame2>, Lagged>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(frame) if self.filter.matches(&frame.message) => return Some(Ok(frame)),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(dropped)) => {
+                    warn!("Subscriber {:?} lagged behind, {} frames dropped", self.filter, dropped);
+                    return Some(Err(Lagged(dropped)));
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+    pub fn filter(&self) -> &MessageFilter {
+        &self.filter
     }
 }
 
-mod codec {
+pub mod codec {
     use std::io;
+    use std::sync::{Arc, Mutex};
 
-    use bytes::{Buf, BufMut, BytesMut};
+    use bytes::{BufMut, BytesMut};
     use deku::prelude::*;
     use tokio_util::codec::{Decoder, Encoder};
-    use tracing::{debug, trace, warn};
+    use tracing::{debug, trace};
 
     use crate::proto;
+    use crate::proto::parser::{Diagnostic, FrameParser, Parsed};
+    use crate::stats::LinkStats;
 
-    #[derive(Debug)]
-    pub struct RefereeCodec;
+    #[derive(Debug, Default)]
+    pub struct RefereeCodec {
+        parser: FrameParser,
+        stats: Arc<Mutex<LinkStats>>,
+    }
 
+    /// 损坏的数据与阻塞客户端一样以 [`Diagnostic`] 报告
     #[derive(thiserror::Error, Debug)]
     pub enum RefereeCodecError {
         #[error("Internal decoder error")]
         Deku(#[from] DekuError),
         #[error("IO error")]
         Io(#[from] io::Error),
+        /// 待发送的帧无法编码，例如 `data_length` 与消息不符
+        #[error("Invalid frame")]
+        Frame(#[from] proto::FrameError),
+        #[error(transparent)]
+        Diagnostic(#[from] Diagnostic),
+        #[error("Stream ended inside a frame, {} bytes left", .bytes.len())]
+        Truncated { bytes: Vec<u8> },
     }
 
-    impl RefereeCodec {}
+    impl RefereeCodec {
+        pub fn new(version: proto::ProtocolVersion) -> Self {
+            Self::with_stats(version, Default::default())
+        }
 
+        /// 重连后沿用同一份链路统计
+        pub fn with_stats(version: proto::ProtocolVersion, stats: Arc<Mutex<LinkStats>>) -> Self {
+            Self { parser: FrameParser::new(version), stats }
+        }
+
+        /// 与解码过程共享的链路统计
+        pub fn stats(&self) -> Arc<Mutex<LinkStats>> {
+            self.stats.clone()
+        }
+    }
+
+    /// 损坏的数据作为 `Ok(Some(Err(..)))` 产出，不会像 `Err` 那样终止 `Framed` 流
     impl Decoder for RefereeCodec {
-        type Item = proto::Frame2;
+        type Item = Result<proto::Frame2, RefereeCodecError>;
         type Error = RefereeCodecError;
 
         fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-            if src.len() < 5 { return Ok(None); }
-            if src[0] != 0xA5 {
-                if let Some(sof_index) = src[1..].iter().position(|&b| b == 0xA5) {
-                    let skipped = src.split_to(sof_index);
-                    debug!("Fast-forwarding {} bytes for 0xA5: {}", sof_index, hex::encode(skipped));
-                    if src.len() < 5 { return Ok(None); }
-                } else {
-                    return Ok(None);
+            match self.parser.parse_from(src) {
+                Some(Parsed::Frame(frame)) => {
+                    trace!("Frame: {:?}", frame);
+                    self.stats.lock().unwrap().record_frame(&frame);
+                    Ok(Some(Ok(frame)))
                 }
+                Some(Parsed::Diagnostic(diagnostic)) => {
+                    debug!("{}", diagnostic);
+                    self.stats.lock().unwrap().record_diagnostic(&diagnostic);
+                    Ok(Some(Err(diagnostic.into())))
+                }
+                None => Ok(None),
             }
+        }
 
-            trace!("0xA5 is aligned");
-
-            let calculated_crc = proto::crc::CRC_8.checksum(&src[0..4]);
-            if calculated_crc != src[4] {
-                debug!("Wrong CRC8: {} summed to {:x} instead of {:x}, fast-forwarding", hex::encode(&src[0..4]), calculated_crc,src[4]);
-                src.advance(1);
-                return Ok(None);
-            }
-
-
-            let data_length = u16::from_le_bytes([src[1], src[2]]) as usize;
-            if data_length > 119 {
-                debug!("Invalid data length: {}", data_length);
-                src.advance(1);
-                return Ok(None);
-            }
-            if src.len() < 5 + 2 + data_length + 2 {
-                trace!("Not enough bytes for a complete frame ({} < {})", src.len(), 5 + 2 + data_length + 2);
-                return Ok(None);
-            }
-
-
-            let calculated_crc = proto::crc::CRC_16.checksum(&src[0..7 + data_length]);
-            if calculated_crc != u16::from_le_bytes([src[7 + data_length], src[7 + data_length + 1]]) {
-                debug!("Wrong CRC16: {} summed to {:x} instead of {:x}, fast-forwarding",
-                    hex::encode(&src[0..7 + data_length]), calculated_crc, u16::from_le_bytes([src[7 + data_length], src[7 + data_length + 1]]) );
-                src.advance(1);
-                return Ok(None);
-            }
-
-            let frame_bytes = src.split_to(7 + data_length + 2);
-            let ((_, rest_byte_size), frame) = proto::Frame2::from_bytes((frame_bytes.as_ref(), 0))?;
-            if rest_byte_size != 0 {
-                warn!("Frame2::from_bytes returned rest_byte_size != 0");
+        fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+            match self.decode(buf)? {
+                Some(item) => Ok(Some(item)),
+                None if buf.is_empty() => Ok(None),
+                None => Ok(Some(Err(RefereeCodecError::Truncated { bytes: buf.split().to_vec() }))),
             }
-            trace!("Frame: {:?}", frame);
-            Ok(Some(frame))
         }
     }
 
@@ -240,15 +642,10 @@ mod codec {
         type Error = RefereeCodecError;
 
         fn encode(&mut self, item: proto::Frame2, dst: &mut BytesMut) -> Result<(), Self::Error> {
-            let len = item.data_length;
-            let mut buf = item.to_bytes().map_err(RefereeCodecError::Deku)?;
-            assert_eq!((len + 9) as usize, buf.len());
-            let crc8 = proto::crc::CRC_8.checksum(&buf[..4]);
-            buf[4] = crc8;
-            let crc16 = proto::crc::CRC_16.checksum(&buf[..buf.len() - 2]).to_le_bytes();
-            let len = buf.len();
-            buf[len - 2] = crc16[0];
-            buf[len - 1] = crc16[1];
+            let buf = item.to_wire_bytes().map_err(|err| match err {
+                proto::FrameError::Deku(err) => RefereeCodecError::Deku(err),
+                err => RefereeCodecError::Frame(err),
+            })?;
             dst.reserve(buf.len());
             dst.put_slice(&buf);
             Ok(())
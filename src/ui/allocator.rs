@@ -0,0 +1,118 @@
+//! 为各自绘制界面的模块分配互不冲突的图形名称与图层
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::proto::graphic::MAX_LAYER;
+
+const NAME_ALPHABET: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const NAME_COUNT: u32 = 62 * 62 * 62;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum AllocError {
+    #[error("No free layer left")]
+    NoFreeLayer,
+    #[error("Layer {0} is already leased")]
+    LayerInUse(u8),
+    #[error("Layer {0} is out of range, at most {MAX_LAYER} allowed")]
+    InvalidLayer(u8),
+    #[error("All graphic names are in use")]
+    NamesExhausted,
+    #[error("Lease has already been released")]
+    Released,
+}
+
+/// 独占一个图层的租约，名称在释放前归该租约所有
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LayerLease {
+    id: u64,
+    layer: u8,
+}
+
+impl LayerLease {
+    pub fn layer(&self) -> u8 {
+        self.layer
+    }
+}
+
+/// 名称与图层分配器
+///
+/// 名称由数字与字母组成，释放后可再次分配，不应与手动指定的名称混用。
+#[derive(Debug, Clone, Default)]
+pub struct UiAllocator {
+    next_id: u64,
+    next_name: u32,
+    free_names: BTreeSet<[u8; 3]>,
+    leases: BTreeMap<u8, (u64, Vec<[u8; 3]>)>,
+}
+
+impl UiAllocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 租用编号最小的空闲图层
+    pub fn lease(&mut self) -> Result<LayerLease, AllocError> {
+        let layer = (0..=MAX_LAYER).find(|layer| !self.leases.contains_key(layer))
+            .ok_or(AllocError::NoFreeLayer)?;
+        self.lease_layer(layer)
+    }
+
+    pub fn lease_layer(&mut self, layer: u8) -> Result<LayerLease, AllocError> {
+        if layer > MAX_LAYER {
+            return Err(AllocError::InvalidLayer(layer));
+        }
+        if self.leases.contains_key(&layer) {
+            return Err(AllocError::LayerInUse(layer));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.leases.insert(layer, (id, Vec::new()));
+        Ok(LayerLease { id, layer })
+    }
+
+    /// 为租约分配一个新名称
+    pub fn name(&mut self, lease: LayerLease) -> Result<[u8; 3], AllocError> {
+        self.names(lease)?;
+        let name = match self.free_names.pop_first() {
+            Some(name) => name,
+            None if self.next_name < NAME_COUNT => {
+                let n = self.next_name as usize;
+                self.next_name += 1;
+                [NAME_ALPHABET[n / (62 * 62)], NAME_ALPHABET[n / 62 % 62], NAME_ALPHABET[n % 62]]
+            }
+            None => return Err(AllocError::NamesExhausted),
+        };
+        self.names_mut(lease)?.push(name);
+        Ok(name)
+    }
+
+    /// 租约已分配的名称
+    pub fn names(&self, lease: LayerLease) -> Result<&[[u8; 3]], AllocError> {
+        match self.leases.get(&lease.layer) {
+            Some((id, names)) if *id == lease.id => Ok(names),
+            _ => Err(AllocError::Released),
+        }
+    }
+
+    fn names_mut(&mut self, lease: LayerLease) -> Result<&mut Vec<[u8; 3]>, AllocError> {
+        match self.leases.get_mut(&lease.layer) {
+            Some((id, names)) if *id == lease.id => Ok(names),
+            _ => Err(AllocError::Released),
+        }
+    }
+
+    /// 释放图层及其名称，返回释放的名称
+    ///
+    /// 图层上已绘制的图形需另行删除，见 [`super::UiManager::release`]。
+    pub fn release(&mut self, lease: LayerLease) -> Result<Vec<[u8; 3]>, AllocError> {
+        self.names(lease)?;
+        let (_, names) = self.leases.remove(&lease.layer).unwrap();
+        self.free_names.extend(names.iter().copied());
+        Ok(names)
+    }
+
+    /// 尚未租出的图层
+    pub fn free_layers(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=MAX_LAYER).filter(|layer| !self.leases.contains_key(layer))
+    }
+}
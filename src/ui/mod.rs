@@ -0,0 +1,247 @@
+//! 保留模式的操作手界面：维护期望显示的图形，与客户端上已显示的图形比较后生成最少的绘制消息
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::proto::graphic::{self, GraphicAddOperation, GraphicData, GraphicDeleteOperation, GraphicEnum, GraphicError};
+use crate::proto::{Message, StudentInteractiveData, StudentInteractiveDataType};
+
+mod allocator;
+pub mod render;
+pub mod text;
+
+pub use allocator::{AllocError, LayerLease, UiAllocator};
+
+/// 一个图形及字符图形的内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct UiElement {
+    pub graphic: GraphicData,
+    /// 仅字符图形有内容
+    pub text: Option<[u8; 30]>,
+}
+
+/// 一条待发送的界面更新
+///
+/// 发送成功后应调用 [`UiManager::confirm`]，未确认的更新会在下次 [`UiManager::updates`] 中再次生成。
+#[derive(Debug, Clone)]
+pub struct UiUpdate {
+    pub message: Message,
+    changes: Vec<Change>,
+}
+
+#[derive(Debug, Clone)]
+enum Change {
+    Clear,
+    ClearLayer(u8),
+    Set(UiElement),
+    Remove([u8; 3]),
+}
+
+/// 按 `graphic_name` 索引的界面
+///
+/// [`UiManager::updates`] 将新增、修改与删除的图形打包为尽量少的 `GraphicDraw1/2/5/7`，空位以
+/// [`GraphicAddOperation::Nop`] 填充，字符图形各自使用一条 `GraphicDrawCharacter`。
+/// 只有经 [`UiManager::confirm`] 确认的更新才被视为已显示在客户端上。
+#[derive(Debug, Clone)]
+pub struct UiManager {
+    send_id: u16,
+    receive_id: u16,
+    desired: BTreeMap<[u8; 3], UiElement>,
+    shown: BTreeMap<[u8; 3], UiElement>,
+    /// 等待以 `DeleteOne` 清空的图层
+    deleted_layers: BTreeSet<u8>,
+    /// 等待以 `DeleteAll` 清空整个界面
+    reset: bool,
+}
+
+impl UiManager {
+    /// 由 `robot_id` 号机器人绘制到其操作手客户端
+    pub fn new(robot_id: u8) -> Self {
+        Self {
+            send_id: robot_id as u16,
+            receive_id: 0x0100 + robot_id as u16,
+            desired: BTreeMap::new(),
+            shown: BTreeMap::new(),
+            deleted_layers: BTreeSet::new(),
+            reset: false,
+        }
+    }
+
+    /// 添加或替换同名图形，字符图形的内容为空，见 [`UiManager::set_text`]
+    pub fn set(&mut self, graphic: GraphicData) -> Result<(), GraphicError> {
+        let text = matches!(graphic.graphic_data, GraphicEnum::Character(_)).then_some([0; 30]);
+        self.insert(graphic, text)
+    }
+
+    /// 添加或替换同名字符图形
+    pub fn set_text(&mut self, graphic: GraphicData, text: [u8; 30]) -> Result<(), GraphicError> {
+        if !matches!(graphic.graphic_data, GraphicEnum::Character(_)) {
+            return Err(GraphicError::TypeMismatch { graphic_type: graphic.graphic_type, expected: 7 });
+        }
+        self.insert(graphic, Some(text))
+    }
+
+    fn insert(&mut self, mut graphic: GraphicData, text: Option<[u8; 30]>) -> Result<(), GraphicError> {
+        graphic.validate()?;
+        graphic.operate_type = GraphicAddOperation::Add;
+        self.desired.insert(graphic.graphic_name, UiElement { graphic, text });
+        Ok(())
+    }
+
+    /// 移除图形，返回是否存在
+    pub fn remove(&mut self, name: impl AsRef<[u8]>) -> bool {
+        graphic::graphic_name(name).is_ok_and(|name| self.desired.remove(&name).is_some())
+    }
+
+    pub fn get(&self, name: impl AsRef<[u8]>) -> Option<&UiElement> {
+        self.desired.get(&graphic::graphic_name(name).ok()?)
+    }
+
+    /// 移除全部图形
+    pub fn clear(&mut self) {
+        self.desired.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.desired.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.desired.is_empty()
+    }
+
+    /// 移除图层上的全部图形，下次更新时以一条 [`StudentInteractiveDataType::GraphicDelete`] 删除整个图层
+    pub fn delete_layer(&mut self, layer: u8) {
+        self.desired.retain(|_, element| element.graphic.layer != layer);
+        self.deleted_layers.insert(layer);
+    }
+
+    /// 释放租约的图层与名称，并删除该图层上的全部图形
+    pub fn release(&mut self, allocator: &mut UiAllocator, lease: LayerLease) -> Result<(), AllocError> {
+        allocator.release(lease)?;
+        self.delete_layer(lease.layer());
+        Ok(())
+    }
+
+    /// 客户端状态未知时调用，例如操作手客户端重启，下次更新时先以 `DeleteAll` 清空界面再重新添加全部图形
+    pub fn invalidate(&mut self) {
+        self.reset = true;
+    }
+
+    /// 与已确认显示的界面比较，生成需要发送的更新，不改变状态
+    pub fn updates(&self) -> Vec<UiUpdate> {
+        let mut updates = Vec::new();
+        let shown: BTreeMap<&[u8; 3], &UiElement> = if self.reset {
+            let content = StudentInteractiveDataType::GraphicDelete { operate_type: GraphicDeleteOperation::DeleteAll, layer: 0 };
+            updates.push(UiUpdate { message: self.message(content), changes: vec![Change::Clear] });
+            BTreeMap::new()
+        } else {
+            for &layer in &self.deleted_layers {
+                let content = StudentInteractiveDataType::GraphicDelete { operate_type: GraphicDeleteOperation::DeleteOne, layer };
+                updates.push(UiUpdate { message: self.message(content), changes: vec![Change::ClearLayer(layer)] });
+            }
+            self.shown.iter()
+                .filter(|(_, element)| !self.deleted_layers.contains(&element.graphic.layer))
+                .collect()
+        };
+
+        let mut graphics = Vec::new();
+        let mut characters = Vec::new();
+        let mut push = |element: &UiElement, operate_type, change| {
+            let mut graphic = element.graphic.clone();
+            graphic.operate_type = operate_type;
+            match element.text {
+                Some(text) => characters.push(((graphic, text), change)),
+                None => graphics.push((graphic, change)),
+            }
+        };
+        for (&name, shown) in &shown {
+            if !self.desired.contains_key(name) {
+                push(shown, GraphicAddOperation::Delete, Change::Remove(*name));
+            }
+        }
+        for (name, element) in &self.desired {
+            match shown.get(name) {
+                None => push(element, GraphicAddOperation::Add, Change::Set(element.clone())),
+                Some(&shown) if shown != element => push(element, GraphicAddOperation::Modify, Change::Set(element.clone())),
+                Some(_) => {}
+            }
+        }
+
+        while !graphics.is_empty() {
+            let (chunk, changes): (Vec<_>, Vec<_>) = graphics.drain(..graphics.len().min(7)).unzip();
+            let content = match chunk.len() {
+                1 => StudentInteractiveDataType::GraphicDraw1(pad(chunk)),
+                2 => StudentInteractiveDataType::GraphicDraw2(pad(chunk)),
+                3..=5 => StudentInteractiveDataType::GraphicDraw5(pad(chunk)),
+                _ => StudentInteractiveDataType::GraphicDraw7(pad(chunk)),
+            };
+            updates.push(UiUpdate { message: self.message(content), changes });
+        }
+        for (character, change) in characters {
+            let content = StudentInteractiveDataType::GraphicDrawCharacter(character);
+            updates.push(UiUpdate { message: self.message(content), changes: vec![change] });
+        }
+        updates
+    }
+
+    /// 记录更新已送达客户端
+    pub fn confirm(&mut self, update: &UiUpdate) {
+        for change in &update.changes {
+            match change {
+                Change::Clear => {
+                    self.shown.clear();
+                    self.deleted_layers.clear();
+                    self.reset = false;
+                }
+                Change::ClearLayer(layer) => {
+                    self.shown.retain(|_, element| element.graphic.layer != *layer);
+                    self.deleted_layers.remove(layer);
+                }
+                Change::Set(element) => {
+                    self.shown.insert(element.graphic.graphic_name, element.clone());
+                }
+                Change::Remove(name) => {
+                    self.shown.remove(name);
+                }
+            }
+        }
+    }
+
+    fn message(&self, content: StudentInteractiveDataType) -> Message {
+        let mut data = StudentInteractiveData { content_id: 0, send_id: self.send_id, receive_id: self.receive_id, content };
+        data.content_id = data.get_content_id();
+        Message::StudentInteractiveData(data)
+    }
+}
+
+fn pad<const N: usize>(mut graphics: Vec<GraphicData>) -> [GraphicData; N] {
+    graphics.resize_with(N, GraphicData::nop);
+    graphics.try_into().unwrap()
+}
+
+#[cfg(feature = "tokio_client")]
+impl UiManager {
+    /// 依次发送并确认全部更新，失败时未发送的更新留待下次发送
+    pub async fn flush(
+        &mut self,
+        writer: &mut crate::tokio_client::RefereeClientWriter,
+    ) -> Result<(), crate::tokio_client::codec::RefereeCodecError> {
+        for update in self.updates() {
+            writer.send_message(update.message.clone()).await?;
+            self.confirm(&update);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "blocking_client")]
+impl UiManager {
+    /// 依次发送并确认全部更新，失败时未发送的更新留待下次发送
+    pub fn flush_blocking(&mut self, client: &mut crate::blocking_client::RefereeClient) -> anyhow::Result<()> {
+        for update in self.updates() {
+            client.send_message(update.message.clone())?;
+            self.confirm(&update);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+//! 将图形操作渲染为 1920x1080 的客户端界面预览，用于离线检查界面布局
+//!
+//! 客户端以左下角为原点、y 轴向上，SVG 以左上角为原点，渲染时翻转 y 轴。
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::proto::graphic::{GraphicAddOperation, GraphicColor, GraphicData, GraphicDeleteOperation, GraphicEnum};
+use crate::proto::id::Side;
+use crate::proto::{Message, StudentInteractiveDataType};
+
+pub const SCREEN_WIDTH: u16 = 1920;
+pub const SCREEN_HEIGHT: u16 = 1080;
+
+#[cfg(feature = "render_png")]
+#[derive(thiserror::Error, Debug)]
+pub enum RenderError {
+    #[error("Failed to parse rendered SVG")]
+    Svg(#[from] resvg::usvg::Error),
+    #[error("Failed to encode PNG: {0}")]
+    Png(String),
+}
+
+/// 按收到的顺序应用图形操作后客户端上显示的图形
+///
+/// ```
+/// # use rmreco::proto::graphic::{GraphicColor, GraphicData};
+/// # use rmreco::proto::id::Side;
+/// # use rmreco::ui::{render::UiPreview, UiManager};
+/// let mut ui = UiManager::new(3);
+/// ui.set(GraphicData::circle(b"aim", 1, GraphicColor::Green).center(960, 540).radius(30).width(2).build()?)?;
+/// let mut preview = UiPreview::new(Side::Red);
+/// ui.updates().iter().for_each(|update| preview.apply_message(&update.message));
+/// assert!(preview.to_svg().contains("<circle"));
+/// # Ok::<(), rmreco::proto::graphic::GraphicError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct UiPreview {
+    side: Side,
+    /// 背景颜色，`None` 时背景透明
+    pub background: Option<String>,
+    graphics: BTreeMap<[u8; 3], (GraphicData, Option<[u8; 30]>)>,
+}
+
+impl UiPreview {
+    /// [`GraphicColor::RedAndBlue`] 按 `side` 显示为红色或蓝色
+    pub fn new(side: Side) -> Self {
+        Self { side, background: Some("#202020".to_owned()), graphics: BTreeMap::new() }
+    }
+
+    /// 非图形消息被忽略
+    pub fn apply_message(&mut self, message: &Message) {
+        if let Message::StudentInteractiveData(data) = message {
+            self.apply(&data.content);
+        }
+    }
+
+    pub fn apply(&mut self, content: &StudentInteractiveDataType) {
+        match content {
+            StudentInteractiveDataType::PeerToPeerCommunication { .. } => {}
+            StudentInteractiveDataType::GraphicDelete { operate_type, layer } => match operate_type {
+                GraphicDeleteOperation::Nop => {}
+                GraphicDeleteOperation::DeleteOne => self.graphics.retain(|_, (graphic, _)| graphic.layer != *layer),
+                GraphicDeleteOperation::DeleteAll => self.graphics.clear(),
+            },
+            StudentInteractiveDataType::GraphicDraw1(graphics) => graphics.iter().for_each(|graphic| self.apply_graphic(graphic, None)),
+            StudentInteractiveDataType::GraphicDraw2(graphics) => graphics.iter().for_each(|graphic| self.apply_graphic(graphic, None)),
+            StudentInteractiveDataType::GraphicDraw5(graphics) => graphics.iter().for_each(|graphic| self.apply_graphic(graphic, None)),
+            StudentInteractiveDataType::GraphicDraw7(graphics) => graphics.iter().for_each(|graphic| self.apply_graphic(graphic, None)),
+            StudentInteractiveDataType::GraphicDrawCharacter((graphic, text)) => self.apply_graphic(graphic, Some(*text)),
+        }
+    }
+
+    /// 修改不存在的图形时被忽略
+    pub fn apply_graphic(&mut self, graphic: &GraphicData, text: Option<[u8; 30]>) {
+        match graphic.operate_type {
+            GraphicAddOperation::Nop => {}
+            GraphicAddOperation::Add => {
+                self.graphics.insert(graphic.graphic_name, (graphic.clone(), text));
+            }
+            GraphicAddOperation::Modify => {
+                if let Some(shown) = self.graphics.get_mut(&graphic.graphic_name) {
+                    *shown = (graphic.clone(), text);
+                }
+            }
+            GraphicAddOperation::Delete => {
+                self.graphics.remove(&graphic.graphic_name);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.graphics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graphics.is_empty()
+    }
+
+    /// 图层大的图形绘制在上方
+    pub fn to_svg(&self) -> String {
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SCREEN_WIDTH}" height="{SCREEN_HEIGHT}" viewBox="0 0 {SCREEN_WIDTH} {SCREEN_HEIGHT}">"#,
+        );
+        if let Some(background) = &self.background {
+            let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="{}"/>"#, escape(background));
+        }
+        let mut graphics: Vec<_> = self.graphics.values().collect();
+        graphics.sort_by_key(|(graphic, _)| graphic.layer);
+        for (graphic, text) in graphics {
+            self.write_graphic(&mut svg, graphic, text.as_ref());
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn write_graphic(&self, svg: &mut String, graphic: &GraphicData, text: Option<&[u8; 30]>) {
+        let color = self.color(graphic.color);
+        let stroke = |width: u16| format!(r#"fill="none" stroke="{color}" stroke-width="{width}""#);
+        let _ = match &graphic.graphic_data {
+            GraphicEnum::StraightLine(data) => writeln!(
+                svg, r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#,
+                data.start_x, flip(data.start_y), data.end_x, flip(data.end_y), stroke(data.width),
+            ),
+            GraphicEnum::Rectangle(data) => writeln!(
+                svg, r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+                data.start_x.min(data.end_x), flip(data.start_y.max(data.end_y)),
+                data.start_x.abs_diff(data.end_x), data.start_y.abs_diff(data.end_y), stroke(data.width),
+            ),
+            GraphicEnum::Circle(data) => writeln!(
+                svg, r#"<circle cx="{}" cy="{}" r="{}" {}/>"#,
+                data.x, flip(data.y), data.radius, stroke(data.width),
+            ),
+            GraphicEnum::Ellipse(data) => writeln!(
+                svg, r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" {}/>"#,
+                data.x, flip(data.y), data.half_x_length, data.half_y_length, stroke(data.width),
+            ),
+            GraphicEnum::Arc(data) => {
+                let (cx, cy) = (data.x as f64, flip(data.y) as f64);
+                let (rx, ry) = (data.half_x_length as f64, data.half_y_length as f64);
+                // 角度以正上方为 0，顺时针增大
+                let point = |angle: u16| {
+                    let radians = (angle as f64).to_radians();
+                    (cx + rx * radians.sin(), cy - ry * radians.cos())
+                };
+                let sweep = (data.end_angle as i32 - data.start_angle as i32).rem_euclid(360);
+                if sweep == 0 && data.start_angle == data.end_angle {
+                    return;
+                }
+                if sweep == 0 {
+                    writeln!(svg, r#"<ellipse cx="{cx}" cy="{cy}" rx="{rx}" ry="{ry}" {}/>"#, stroke(data.width))
+                } else {
+                    let (x1, y1) = point(data.start_angle);
+                    let (x2, y2) = point(data.end_angle);
+                    writeln!(
+                        svg, r#"<path d="M {x1:.2} {y1:.2} A {rx} {ry} 0 {} 1 {x2:.2} {y2:.2}" {}/>"#,
+                        (sweep > 180) as u8, stroke(data.width),
+                    )
+                }
+            }
+            GraphicEnum::FloatingNumber(data) => {
+                let value = format!("{:.*}", data.decimal_digit as usize, data.value as f64 / 1000.0);
+                write_text(svg, data.start_x, data.start_y, data.font_size, color, &value)
+            }
+            GraphicEnum::Integer(data) => write_text(svg, data.start_x, data.start_y, data.font_size, color, &data.value.to_string()),
+            GraphicEnum::Character(data) => {
+                let text = text.map(|text| {
+                    let length = (data.decimal_digit as usize).min(text.len());
+                    let end = text[..length].iter().position(|&byte| byte == 0).unwrap_or(length);
+                    String::from_utf8_lossy(&text[..end]).into_owned()
+                }).unwrap_or_default();
+                write_text(svg, data.x, data.y, data.font_size, color, &text)
+            }
+        };
+    }
+
+    fn color(&self, color: GraphicColor) -> &'static str {
+        match color {
+            GraphicColor::RedAndBlue => match self.side {
+                Side::Red => "#ff3030",
+                Side::Blue => "#30a0ff",
+            },
+            GraphicColor::Yellow => "#ffff00",
+            GraphicColor::Green => "#00ff00",
+            GraphicColor::Orange => "#ffa500",
+            GraphicColor::PurplishRed => "#c71585",
+            GraphicColor::Pink => "#ff69b4",
+            GraphicColor::Cyan => "#00ffff",
+            GraphicColor::Black => "#000000",
+            GraphicColor::White => "#ffffff",
+        }
+    }
+
+    /// 需要 `render_png` 特性，字符使用系统字体渲染
+    #[cfg(feature = "render_png")]
+    pub fn to_png(&self) -> Result<Vec<u8>, RenderError> {
+        use resvg::usvg::{self, TreeParsing, TreeTextToPath};
+
+        let mut tree = usvg::Tree::from_str(&self.to_svg(), &usvg::Options::default())?;
+        let mut fontdb = usvg::fontdb::Database::new();
+        fontdb.load_system_fonts();
+        tree.convert_text(&fontdb);
+        let mut pixmap = resvg::tiny_skia::Pixmap::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+            .ok_or_else(|| RenderError::Png("Failed to allocate pixmap".to_owned()))?;
+        resvg::Tree::from_usvg(&tree).render(resvg::tiny_skia::Transform::default(), &mut pixmap.as_mut());
+        pixmap.encode_png().map_err(|err| RenderError::Png(err.to_string()))
+    }
+}
+
+fn flip(y: u16) -> i32 {
+    SCREEN_HEIGHT as i32 - y as i32
+}
+
+/// 以 (x, y) 为文字左上角
+fn write_text(svg: &mut String, x: u16, y: u16, font_size: u16, color: &str, text: &str) -> std::fmt::Result {
+    writeln!(
+        svg, r#"<text x="{}" y="{}" font-size="{}" font-family="monospace" fill="{}">{}</text>"#,
+        x, flip(y) + font_size as i32, font_size, color, escape(text),
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,116 @@
+//! 字符图形的编码、截断与多行排版
+
+use crate::proto::graphic::{GraphicColor, GraphicData, GraphicError};
+
+/// 一个字符图形最多容纳的字节数
+pub const TEXT_CAPACITY: usize = 30;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum TextError {
+    #[error("Text is {0} bytes long, at most {TEXT_CAPACITY} allowed")]
+    TooLong(usize),
+    #[error("Text needs {needed} lines but only {given} names were given")]
+    NotEnoughNames { needed: usize, given: usize },
+    #[error("Line {0} is below the bottom of the screen")]
+    OffScreen(usize),
+    #[error(transparent)]
+    Graphic(#[from] GraphicError),
+}
+
+/// 将 `text` 编码为以 0 补齐的内容及其长度，超长时返回错误
+pub fn encode_text(text: &str) -> Result<([u8; TEXT_CAPACITY], usize), TextError> {
+    if text.len() > TEXT_CAPACITY {
+        return Err(TextError::TooLong(text.len()));
+    }
+    let mut content = [0u8; TEXT_CAPACITY];
+    content[..text.len()].copy_from_slice(text.as_bytes());
+    Ok((content, text.len()))
+}
+
+/// 截断到不超过 [`TEXT_CAPACITY`] 字节的字符边界
+pub fn truncate_text(text: &str) -> &str {
+    if text.len() <= TEXT_CAPACITY { return text; }
+    let end = (0..=TEXT_CAPACITY).rev().find(|&end| text.is_char_boundary(end)).unwrap_or(0);
+    &text[..end]
+}
+
+/// 构造以 `pos` 为左上角的单行字符图形，可直接用于 [`super::UiManager::set_text`] 或
+/// [`crate::proto::StudentInteractiveDataType::GraphicDrawCharacter`]，线宽取字号的十分之一
+///
+/// ```
+/// # use rmreco::proto::graphic::GraphicColor;
+/// # use rmreco::ui::text::draw_text;
+/// let (graphic, content) = draw_text(b"cap", 2, GraphicColor::White, (100, 800), 20, "CAP 87% | AMMO 120")?;
+/// assert_eq!(&content[..18], b"CAP 87% | AMMO 120");
+/// # Ok::<(), rmreco::ui::text::TextError>(())
+/// ```
+pub fn draw_text(
+    name: impl AsRef<[u8]>,
+    layer: u8,
+    color: GraphicColor,
+    pos: (u16, u16),
+    font_size: u16,
+    text: &str,
+) -> Result<(GraphicData, [u8; TEXT_CAPACITY]), TextError> {
+    let (content, length) = encode_text(text)?;
+    let graphic = GraphicData::character(name, layer, color)
+        .at(pos.0, pos.1)
+        .font_size(font_size)
+        .width((font_size / 10).max(1))
+        .length(length as u16)
+        .build()?;
+    Ok((graphic, content))
+}
+
+/// 同 [`draw_text`]，超长的部分被截断
+pub fn draw_text_truncated(
+    name: impl AsRef<[u8]>,
+    layer: u8,
+    color: GraphicColor,
+    pos: (u16, u16),
+    font_size: u16,
+    text: &str,
+) -> Result<(GraphicData, [u8; TEXT_CAPACITY]), TextError> {
+    draw_text(name, layer, color, pos, font_size, truncate_text(text))
+}
+
+/// 按换行符及 [`TEXT_CAPACITY`] 拆分为多行，依次使用 `names` 中的名称，行距为两倍字号
+///
+/// 超长的行在最后一个空格处换行，没有空格时在字符边界处断开。
+pub fn draw_text_lines(
+    names: impl IntoIterator<Item = [u8; 3]>,
+    layer: u8,
+    color: GraphicColor,
+    pos: (u16, u16),
+    font_size: u16,
+    text: &str,
+) -> Result<Vec<(GraphicData, [u8; TEXT_CAPACITY])>, TextError> {
+    let lines: Vec<&str> = text.lines().flat_map(wrap).collect();
+    let names: Vec<[u8; 3]> = names.into_iter().take(lines.len()).collect();
+    if names.len() < lines.len() {
+        return Err(TextError::NotEnoughNames { needed: lines.len(), given: names.len() });
+    }
+    lines.iter().zip(names).enumerate()
+        .map(|(index, (line, name))| {
+            let y = (pos.1 as u32).checked_sub(index as u32 * font_size as u32 * 2)
+                .ok_or(TextError::OffScreen(index))? as u16;
+            draw_text(name, layer, color, (pos.0, y), font_size, line)
+        })
+        .collect()
+}
+
+fn wrap(mut line: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    while line.len() > TEXT_CAPACITY {
+        let head = truncate_text(line);
+        let window = if line[head.len()..].starts_with(' ') { &line[..head.len() + 1] } else { head };
+        let (taken, rest) = match window.rfind(' ') {
+            Some(space) if space > 0 => (&line[..space], &line[space + 1..]),
+            _ => (head, &line[head.len()..]),
+        };
+        lines.push(taken);
+        line = rest;
+    }
+    lines.push(line);
+    lines
+}